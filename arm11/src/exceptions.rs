@@ -48,21 +48,31 @@ pub unsafe extern fn svc_handler() {
 #[naked]
 pub unsafe extern fn undefined_instruction_handler() {
     set_stack_pointer(0x24000000);
-    panic!("undefined instruction");
+    panic!("undefined instruction at 0x{:08X}", link_register() - 4);
 }
 
 #[no_mangle]
 #[naked]
 pub unsafe extern fn prefetch_abort_handler() {
     set_stack_pointer(0x24000000);
-    panic!("prefetch abort");
+    panic!("prefetch abort at 0x{:08X}", link_register() - 4);
 }
 
 #[no_mangle]
 #[naked]
 pub unsafe extern fn data_abort_handler() {
     set_stack_pointer(0x24000000);
-    panic!("data abort");
+    panic!("data abort at 0x{:08X}", link_register() - 8);
+}
+
+/// Returns the return address of the exception,
+/// which lies a fixed distance behind the faulting instruction.
+/// Pass it to `firmtool symbolize` to get the function name.
+#[inline(always)]
+pub unsafe fn link_register() -> usize {
+    let lr: usize;
+    asm!("mov $0, lr" : "=r"(lr));
+    lr
 }
 
 #[inline(always)]
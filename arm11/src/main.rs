@@ -8,12 +8,13 @@
 use volatile::Volatile;
 use lcd::*;
 use gpu::FramebufferConfig;
-use core::ptr::{read_volatile, write_volatile};
-use core::{str, fmt, cmp, mem};
+use core::ptr::read_volatile;
+use core::{str, fmt, mem};
 use core::fmt::{Write, UpperHex, Binary};
 use common::input::GamePad;
 use common::util::reg::*;
 use common::Console;
+use common::framebuffer::{Framebuffer, PixelFormat};
 use common::mem::arm11::*;
 use num_traits::PrimInt;
 
@@ -27,8 +28,9 @@ mod exceptions;
 const SCREEN_TOP_WIDTH: usize = 400;
 const SCREEN_BOTTOM_WIDTH: usize = 320;
 const SCREEN_HEIGHT: usize = 240;
-const SCREEN_TOP_FBSIZE: usize = (3 * SCREEN_TOP_WIDTH * SCREEN_HEIGHT);
-const SCREEN_BOTTOM_FBSIZE: usize = (3 * SCREEN_BOTTOM_WIDTH * SCREEN_HEIGHT);
+const SCREEN_FORMAT: PixelFormat = PixelFormat::Bgr8;
+const SCREEN_TOP_FBSIZE: usize = SCREEN_FORMAT.bytes_per_pixel() * SCREEN_TOP_WIDTH * SCREEN_HEIGHT;
+const SCREEN_BOTTOM_FBSIZE: usize = SCREEN_FORMAT.bytes_per_pixel() * SCREEN_BOTTOM_WIDTH * SCREEN_HEIGHT;
 
 global_asm!(r#"
 .section .text.start
@@ -46,7 +48,14 @@ _start:
 .pool
 "#);
 
-const FERRIS: &[u8] = include_bytes!("../../ferris.data");
+/// Generated from ferris.png by `firmtool image`, already in the framebuffer layout
+const FERRIS: &[u8] = include_bytes!("../../ferris.bin");
+const FERRIS_FORMAT: PixelFormat = PixelFormat::Bgr8;
+
+const _: () = assert!(
+    FERRIS.len() == FERRIS_FORMAT.bytes_per_pixel() * SCREEN_TOP_WIDTH * SCREEN_HEIGHT,
+    "ferris.bin has to cover the whole top screen in BGR8, regenerate it with `firmtool image`",
+);
 
 #[no_mangle]
 pub unsafe extern "C" fn _rust_start() -> ! {
@@ -65,19 +74,14 @@ pub unsafe extern "C" fn _rust_start() -> ! {
 
     busy_sleep(1000);
 
-    let fb_top = core::slice::from_raw_parts_mut::<[u8; 3]>(0x18000000 as *mut _, SCREEN_TOP_FBSIZE / 3);
+    let fb_top = core::slice::from_raw_parts_mut::<u8>(0x18000000 as *mut _, SCREEN_TOP_FBSIZE);
+    let mut fb_top = Framebuffer::new(fb_top, SCREEN_TOP_WIDTH, SCREEN_HEIGHT, SCREEN_FORMAT);
 
-    init_screens(fb_top);
+    init_screens(&fb_top);
 
-    {
-        for (pixel, ferris_pixel) in fb_top.iter_mut().zip(FERRIS.chunks(3)) {
-            write_volatile(&mut pixel[0], ferris_pixel[2]);
-            write_volatile(&mut pixel[1], ferris_pixel[1]);
-            write_volatile(&mut pixel[2], ferris_pixel[0]);
-        }
-    }
+    fb_top.blit(0, 0, &Framebuffer::new(FERRIS, SCREEN_TOP_WIDTH, SCREEN_HEIGHT, FERRIS_FORMAT));
 
-    let ref mut console = Console::new(fb_top, 400, 240);
+    let ref mut console = Console::new(fb_top);
 
     let mut pad = GamePad::new();
     let mut bg_color = U32HexEditor::new(0);
@@ -249,7 +253,11 @@ impl U32HexEditor {
     }
 }
 
-pub unsafe fn init_screens(top_fb: &mut [[u8; 3]]) {
+pub unsafe fn init_screens(top_fb: &Framebuffer<&mut [u8]>) {
+    let buffer_format = top_fb.format() as u32;
+    let stride = top_fb.stride() as u32;
+    let top_fb = top_fb.as_bytes();
+
     let brightness_level = 0xFEFE;
 
     (*(0x10141200 as *mut Volatile<u32>)).write(0x1007F);
@@ -293,14 +301,14 @@ pub unsafe fn init_screens(top_fb: &mut [[u8; 3]]) {
     top_fb_conf.set_buffer0(top_fb.as_ptr() as _);
     top_fb_conf.set_buffer1(top_fb.as_ptr() as _);
 
-    top_fb_conf.set_buffer_format(0x80341);
+    top_fb_conf.set_buffer_format(0x80340 | buffer_format);
     top_fb_conf.reg(0x74).write(0x10501);
     top_fb_conf.set_shown_buffer(0);
 
     top_fb_conf.set_alt_buffer0(top_fb.as_ptr() as _);
     top_fb_conf.set_alt_buffer1(top_fb.as_ptr() as _);
 
-    top_fb_conf.set_buffer_stride(0x2D0);
+    top_fb_conf.set_buffer_stride(stride);
     top_fb_conf.reg(0x9C).write(0);
 
     // Set up color LUT
@@ -309,10 +317,10 @@ pub unsafe fn init_screens(top_fb: &mut [[u8; 3]]) {
         top_fb_conf.set_color_lut_color(0x10101 * i);
     }
 
-    setup_framebuffers(top_fb.as_ptr() as _);
+    setup_framebuffers(top_fb.as_ptr() as _, buffer_format, stride);
 }
 
-unsafe fn setup_framebuffers(addr: u32) {
+unsafe fn setup_framebuffers(addr: u32, buffer_format: u32, stride: u32) {
     (*(0x10202204 as *mut Volatile<u32>)).write(0x01000000); //set LCD fill black to hide potential garbage -- NFIRM does it before firmlaunching
     (*(0x10202A04 as *mut Volatile<u32>)).write(0x01000000);
 
@@ -325,12 +333,12 @@ unsafe fn setup_framebuffers(addr: u32) {
     // (*(0x1040056c as *mut Volatile<u32>)).write((u32)fbs[1].bottom);
 
     //Set framebuffer format, framebuffer select and stride
-    top_fb_conf.reg(0x70).write(0x80341);
+    top_fb_conf.reg(0x70).write(0x80340 | buffer_format);
     top_fb_conf.reg(0x78).write(0);
-    top_fb_conf.reg(0x90).write(0x2D0);
-    (*(0x10400570 as *mut Volatile<u32>)).write(0x80301);
+    top_fb_conf.reg(0x90).write(stride);
+    (*(0x10400570 as *mut Volatile<u32>)).write(0x80300 | buffer_format);
     (*(0x10400578 as *mut Volatile<u32>)).write(0);
-    (*(0x10400590 as *mut Volatile<u32>)).write(0x2D0);
+    (*(0x10400590 as *mut Volatile<u32>)).write(stride);
 
     (*(0x10202204 as *mut Volatile<u32>)).write(0x00000000); //unset LCD fill
     (*(0x10202A04 as *mut Volatile<u32>)).write(0x00000000);
@@ -1,14 +1,16 @@
 use core::slice;
 use core::fmt::Write;
 use common::Console;
+use common::framebuffer::{Framebuffer, PixelFormat};
 
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     let fb_top = unsafe {
-        slice::from_raw_parts_mut::<[u8; 3]>(0x18000000 as *mut _, 240 * 400)
+        slice::from_raw_parts_mut::<u8>(0x18000000 as *mut _, 3 * 240 * 400)
     };
+    let fb_top = Framebuffer::new(fb_top, 400, 240, PixelFormat::Bgr8);
 
-    let ref mut console = Console::new(fb_top, 400, 240);
+    let ref mut console = Console::new(fb_top);
     console.clear([0, 0, 255]);
     console.set_fg([255, 0, 0]);
     console.set_bg([0; 3]);
@@ -1,30 +1,55 @@
-use core::ptr::{read_volatile, write_volatile};
+use core::cmp::min;
 use core::fmt;
 use core::mem;
-
+use crate::framebuffer::{Color, Framebuffer};
+use self::ansi::{Action, Params, Parser};
+use font8x8::unicode::{
+    FontUnicode, BASIC_UNICODE, LATIN_UNICODE, GREEK_UNICODE,
+    BOX_UNICODE, BLOCK_UNICODE, HIRAGANA_UNICODE, MISC_UNICODE,
+};
+
+pub mod ansi;
+
+const TAB_WIDTH: usize = 8;
+
+/// A text console on a framebuffer.
+///
+/// Besides `\n`, `\r`, `\t` and backspace, a subset of ANSI escape sequences is understood:
+/// SGR colors (including 256 color and truecolor), inverse video, cursor movement,
+/// clearing lines and the screen, and saving/restoring the cursor position.
 pub struct Console<'a> {
-    buf: &'a mut [[u8; 3]],
+    fb: Framebuffer<&'a mut [u8]>,
     width: usize,
     height: usize,
     x_pos: usize,
     y_pos: usize,
     bg_color: [u8; 3],
     fg_color: [u8; 3],
+    default_bg_color: [u8; 3],
+    default_fg_color: [u8; 3],
+    inverse: bool,
+    saved_pos: (usize, usize),
+    parser: Parser,
 }
 
 impl<'a> Console<'a> {
-    pub fn new(buf: &'a mut [[u8; 3]], width: usize, height: usize) -> Self {
-        let width = width / 8;
-        let height = height / 8;
+    pub fn new(fb: Framebuffer<&'a mut [u8]>) -> Self {
+        let width = fb.width() / 8;
+        let height = fb.height() / 8;
 
         Self {
-            buf,
+            fb,
             width,
             height,
             x_pos: 0,
             y_pos: 0,
             bg_color: [0; 3],
             fg_color: [255; 3],
+            default_bg_color: [0; 3],
+            default_fg_color: [255; 3],
+            inverse: false,
+            saved_pos: (0, 0),
+            parser: Parser::new(),
         }
     }
 
@@ -37,137 +62,294 @@ impl<'a> Console<'a> {
         self.y_pos = y;
     }
 
+    /// The cursor position as column and row.
+    pub fn position(&self) -> (usize, usize) {
+        (self.x_pos, self.y_pos)
+    }
+
+    /// Sets the foreground color, which is also what `ESC [ 39 m` and `ESC [ 0 m` return to.
     pub fn set_fg(&mut self, color: [u8; 3]) {
         self.fg_color = color;
+        self.default_fg_color = color;
     }
 
+    /// Sets the background color, which is also what `ESC [ 49 m` and `ESC [ 0 m` return to.
     pub fn set_bg(&mut self, color: [u8; 3]) {
         self.bg_color = color;
+        self.default_bg_color = color;
     }
 
-    pub fn clear(&mut self, mut color: [u8; 3]) {
+    pub fn clear(&mut self, color: [u8; 3]) {
         self.x_pos = 0;
         self.y_pos = 0;
 
-        color.reverse();
+        self.fb.fill(color.into());
+    }
 
-        for pixel in &mut self.buf[..] {
-            unsafe {
-                write_volatile(pixel, color);
-            }
-        }
+    pub fn framebuffer(&mut self) -> &mut Framebuffer<&'a mut [u8]> {
+        &mut self.fb
     }
 
     pub fn write_str(&mut self, str: &str) {
-        self.write(str.as_bytes());
+        for ch in str.chars() {
+            self.write_char(ch);
+        }
     }
 
+    /// Writes UTF-8 encoded text, invalid sequences show up as replacement glyphs.
     pub fn write(&mut self, bytes: &[u8]) {
-        for &byte in bytes {
-            self.write_byte(byte);
+        for chunk in bytes.utf8_chunks() {
+            self.write_str(chunk.valid());
+
+            if !chunk.invalid().is_empty() {
+                self.write_char(char::REPLACEMENT_CHARACTER);
+            }
         }
     }
 
-    fn write_byte(&mut self, byte: u8) {
-        if byte == b'\n' {
-            self.write_newline();
-            return;
+    pub fn write_char(&mut self, ch: char) {
+        match self.parser.advance(ch) {
+            Some(Action::Print(ch)) => self.print(ch),
+            Some(Action::Control(ch)) => self.control(ch),
+            Some(Action::Escape(ch)) => self.escape(ch),
+            Some(Action::Csi(params, action)) => self.csi(&params, action),
+            None => {},
         }
+    }
 
+    fn print(&mut self, ch: char) {
         if self.x_pos >= self.width {
             self.write_newline();
         }
 
-        self.render_char(self.x_pos, self.y_pos, byte);
+        self.render_char(self.x_pos, self.y_pos, ch);
 
         self.x_pos += 1;
     }
 
-    fn write_newline(&mut self) {
-        self.y_pos += 1;
-        self.x_pos = 0;
+    fn control(&mut self, ch: char) {
+        match ch {
+            '\n' => self.write_newline(),
+            '\r' => self.x_pos = 0,
+            '\t' => self.x_pos = min((self.x_pos / TAB_WIDTH + 1) * TAB_WIDTH, self.width),
+            '\x08' => self.x_pos = min(self.x_pos, self.width).saturating_sub(1),
+            _ => {},
+        }
+    }
 
-        if self.y_pos >= self.height {
-            self.shift_buffer_up();
-            self.y_pos -= 1;
+    fn escape(&mut self, ch: char) {
+        match ch {
+            '7' => self.save_cursor(),
+            '8' => self.restore_cursor(),
+            _ => {},
         }
     }
 
-    fn shift_buffer_up(&mut self) {
-        for y in 8..(8 * self.height) {
-            for x in 0..(8 * self.width) {
-                let from = self.pos(x, y);
-                let to = self.pos(x, y - 8);
-
-                unsafe {
-                    write_volatile(&mut self.buf[to], read_volatile(&self.buf[from]));
-                }
+    fn csi(&mut self, params: &Params, action: char) {
+        let n = params.get_or(0, 1) as usize;
+        let max_x = self.width.saturating_sub(1);
+        let max_y = self.height.saturating_sub(1);
+
+        match action {
+            'A' => self.y_pos = self.y_pos.saturating_sub(n),
+            'B' => self.y_pos = min(self.y_pos + n, max_y),
+            'C' => self.x_pos = min(self.x_pos + n, max_x),
+            'D' => self.x_pos = min(self.x_pos, max_x).saturating_sub(n),
+            'E' => self.go_to(0, min(self.y_pos + n, max_y)),
+            'F' => self.go_to(0, self.y_pos.saturating_sub(n)),
+            'G' => self.x_pos = min(n - 1, max_x),
+            'H' | 'f' => {
+                let y = params.get_or(0, 1) as usize - 1;
+                let x = params.get_or(1, 1) as usize - 1;
+
+                self.go_to(min(x, max_x), min(y, max_y));
+            },
+            'J' => self.erase_screen(params.get_or(0, 0)),
+            'K' => self.erase_line(params.get_or(0, 0)),
+            'm' => self.sgr(params.as_slice()),
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            _ => {},
+        }
+    }
+
+    /// Select Graphic Rendition, `ESC [ ... m`
+    fn sgr(&mut self, params: &[u16]) {
+        let mut params = params.iter().copied();
+
+        while let Some(param) = params.next() {
+            match param {
+                0 => {
+                    self.fg_color = self.default_fg_color;
+                    self.bg_color = self.default_bg_color;
+                    self.inverse = false;
+                },
+                7 => self.inverse = true,
+                27 => self.inverse = false,
+                30..=37 => self.fg_color = ansi::palette(param as u8 - 30),
+                40..=47 => self.bg_color = ansi::palette(param as u8 - 40),
+                90..=97 => self.fg_color = ansi::palette(param as u8 - 90 + 8),
+                100..=107 => self.bg_color = ansi::palette(param as u8 - 100 + 8),
+                39 => self.fg_color = self.default_fg_color,
+                49 => self.bg_color = self.default_bg_color,
+                38 => if let Some(color) = extended_color(&mut params) {
+                    self.fg_color = color;
+                },
+                48 => if let Some(color) = extended_color(&mut params) {
+                    self.bg_color = color;
+                },
+                // Bold, underline, blink etc. have no representation in an 8x8 font
+                _ => {},
             }
         }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_pos = (self.x_pos, self.y_pos);
+    }
+
+    fn restore_cursor(&mut self) {
+        let (x, y) = self.saved_pos;
 
-        for y_off in 0..8 {
-            for x in 0..(8 * self.width) {
-                let to = self.pos(x, 8 * self.height - 1 - y_off);
+        self.go_to(x, y);
+    }
+
+    /// `ESC [ n J`: 0 clears from the cursor to the end of the screen,
+    /// 1 from the start of the screen to the cursor and 2 or 3 the whole screen.
+    fn erase_screen(&mut self, mode: u16) {
+        let (x, y) = (self.x_pos, self.y_pos);
+
+        match mode {
+            0 => {
+                self.erase_line(0);
+                self.erase_rows(y + 1..self.height);
+            },
+            1 => {
+                self.erase_rows(0..y);
+                self.erase_line(1);
+            },
+            2 | 3 => self.erase_rows(0..self.height),
+            _ => {},
+        }
 
-                unsafe {
-                    write_volatile(&mut self.buf[to], [0, 0, 0]);
-                }
+        self.go_to(x, y);
+    }
+
+    /// `ESC [ n K`: 0 clears from the cursor to the end of the line,
+    /// 1 from the start of the line to the cursor and 2 the whole line.
+    fn erase_line(&mut self, mode: u16) {
+        let columns = match mode {
+            0 => self.x_pos..self.width,
+            1 => 0..min(self.x_pos + 1, self.width),
+            2 => 0..self.width,
+            _ => return,
+        };
+
+        for x in columns {
+            self.erase_cell(x, self.y_pos);
+        }
+    }
+
+    fn erase_rows(&mut self, rows: core::ops::Range<usize>) {
+        for y in rows {
+            for x in 0..self.width {
+                self.erase_cell(x, y);
             }
         }
     }
 
-    fn render_char(&mut self, x: usize, y: usize, ch: u8) {
-        use font8x8::unicode::BASIC_UNICODE;
+    fn write_newline(&mut self) {
+        self.y_pos += 1;
+        self.x_pos = 0;
+
+        if self.y_pos >= self.height {
+            self.shift_buffer_up();
+            self.y_pos -= 1;
+        }
+    }
+
+    fn shift_buffer_up(&mut self) {
+        self.fb.scroll(8, self.bg_color.into());
+    }
 
+    fn render_char(&mut self, x: usize, y: usize, ch: char) {
         if x >= self.width || y >= self.height {
             return;
         }
 
         let x = 8 * x;
         let y = 8 * y;
-
-        let glyph = match BASIC_UNICODE.get(ch as usize) {
-            None => return self.render_char(x, y, 0),
-            Some(glyph) => glyph,
+        let (fg_color, bg_color): (Color, Color) = match self.inverse {
+            false => (self.fg_color.into(), self.bg_color.into()),
+            true => (self.bg_color.into(), self.fg_color.into()),
         };
 
-        for (y_off, row) in glyph.byte_array().iter().copied().enumerate() {
+        for (y_off, row) in glyph(ch).iter().copied().enumerate() {
             let y = y + y_off;
 
             for x_off in 0..8u8 {
                 let x = x + x_off as usize;
                 let luminance = (row >> x_off) & 1;
                 let color = match luminance {
-                    0 => self.bg_color,
-                    _ => self.fg_color,   
+                    0 => bg_color,
+                    _ => fg_color,
                 };
 
-                self.blit(x, y, color);
+                self.fb.set_pixel(x, y, color);
             }
         }
     }
 
-    fn blit(&mut self, x: usize, y: usize, color: [u8; 3]) {
-        let pos = self.pos(x, y);
-
-        if pos >= self.buf.len() {
+    /// Fills a cell with the background color, erasing is not affected by inverse video.
+    fn erase_cell(&mut self, x: usize, y: usize) {
+        if x >= self.width || y >= self.height {
             return;
         }
 
-        unsafe {
-            write_volatile(&mut self.buf[pos][0], color[2]);
-            write_volatile(&mut self.buf[pos][1], color[1]);
-            write_volatile(&mut self.buf[pos][2], color[0]);
-        }
+        self.fb.fill_rect(8 * x, 8 * y, 8, 8, self.bg_color.into());
     }
+}
+
+/// Reads the color of an extended SGR color parameter,
+/// `5;n` for the 256 color palette or `2;r;g;b` for truecolor.
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<[u8; 3]> {
+    let mut component = || params.next().map(|value| min(value, 255) as u8);
 
-    fn pos(&self, x: usize, y: usize) -> usize {
-        let y = 8 * self.height - 1 - y;
-        let pos = 8 * self.height * x + y;
-        pos
+    match component()? {
+        5 => component().map(ansi::palette),
+        2 => Some([component()?, component()?, component()?]),
+        _ => None,
     }
 }
 
+/// Looks up the bitmap of `ch` in the font8x8 tables.
+/// Characters the font does not cover are drawn as an inverted question mark.
+fn glyph(ch: char) -> [u8; 8] {
+    let glyph = match ch {
+        '\u{0000}'..='\u{007F}' => find_glyph(&BASIC_UNICODE, '\u{0000}', ch),
+        '\u{00A0}'..='\u{00FF}' => find_glyph(&LATIN_UNICODE, '\u{00A0}', ch),
+        '\u{0390}'..='\u{03C9}' => find_glyph(&GREEK_UNICODE, '\u{0390}', ch),
+        '\u{2500}'..='\u{257F}' => find_glyph(&BOX_UNICODE, '\u{2500}', ch),
+        '\u{2580}'..='\u{259F}' => find_glyph(&BLOCK_UNICODE, '\u{2580}', ch),
+        '\u{3040}'..='\u{309F}' => find_glyph(&HIRAGANA_UNICODE, '\u{3040}', ch),
+        _ => None,
+    };
+
+    glyph
+        .or_else(|| find_glyph(&MISC_UNICODE, '\u{0000}', ch))
+        .unwrap_or_else(|| BASIC_UNICODE[b'?' as usize].byte_array().map(|row| !row))
+}
+
+/// Tables are mostly contiguous, so try indexing by code point before searching.
+fn find_glyph(table: &[FontUnicode], first: char, ch: char) -> Option<[u8; 8]> {
+    let index = (ch as usize).wrapping_sub(first as usize);
+
+    table.get(index)
+        .filter(|glyph| glyph.char() == ch)
+        .or_else(|| table.iter().find(|glyph| glyph.char() == ch))
+        .map(FontUnicode::byte_array)
+}
+
 impl<'a> fmt::Write for Console<'a> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.write_str(s);
@@ -0,0 +1,178 @@
+//! Parser for the subset of ANSI/VT100 escape sequences understood by [`Console`].
+//!
+//! [`Console`]: super::Console
+
+const MAX_PARAMS: usize = 16;
+
+const ESC: char = '\x1b';
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+    /// Sequences that are parsed to their end but otherwise ignored,
+    /// such as private modes like `ESC [ ? 25 l`
+    IgnoreCsi,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Params {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+    /// Set once more than `MAX_PARAMS` parameters were given, the rest is dropped
+    overflow: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Print(char),
+    Control(char),
+    Escape(char),
+    Csi(Params, char),
+}
+
+pub struct Parser {
+    state: State,
+    params: Params,
+}
+
+impl Params {
+    const fn new() -> Self {
+        Self {
+            values: [0; MAX_PARAMS],
+            len: 1,
+            overflow: false,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u16] {
+        &self.values[..self.len]
+    }
+
+    /// Returns the parameter at `index`, substituting `default` for missing or zero values.
+    pub fn get_or(&self, index: usize, default: u16) -> u16 {
+        match self.as_slice().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+
+    fn push_digit(&mut self, digit: u32) {
+        if self.overflow {
+            return;
+        }
+
+        let value = &mut self.values[self.len - 1];
+
+        *value = value.saturating_mul(10).saturating_add(digit as u16);
+    }
+
+    fn next(&mut self) {
+        if self.len < MAX_PARAMS {
+            self.len += 1;
+        } else {
+            self.overflow = true;
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: Params::new(),
+        }
+    }
+
+    /// Feeds one char into the parser, returning what the console should do with it, if anything.
+    pub fn advance(&mut self, ch: char) -> Option<Action> {
+        match self.state {
+            State::Ground => match ch {
+                ESC => {
+                    self.state = State::Escape;
+                    None
+                },
+                '\0'..='\x1f' | '\x7f' => Some(Action::Control(ch)),
+                _ => Some(Action::Print(ch)),
+            },
+            State::Escape => match ch {
+                '[' => {
+                    self.state = State::Csi;
+                    self.params = Params::new();
+                    None
+                },
+                ESC => None,
+                _ => {
+                    self.state = State::Ground;
+                    Some(Action::Escape(ch))
+                },
+            },
+            State::Csi | State::IgnoreCsi => match ch {
+                '0'..='9' => {
+                    self.params.push_digit(ch as u32 - '0' as u32);
+                    None
+                },
+                ';' | ':' => {
+                    self.params.next();
+                    None
+                },
+                '<'..='?' | ' '..='/' => {
+                    self.state = State::IgnoreCsi;
+                    None
+                },
+                // A new sequence cancels the current one
+                ESC => {
+                    self.state = State::Escape;
+                    None
+                },
+                '@'..='~' => {
+                    let ignore = self.state == State::IgnoreCsi;
+
+                    self.state = State::Ground;
+
+                    match ignore {
+                        true => None,
+                        false => Some(Action::Csi(self.params, ch)),
+                    }
+                },
+                // Anything else aborts the sequence
+                _ => {
+                    self.state = State::Ground;
+                    None
+                },
+            },
+        }
+    }
+}
+
+/// Looks up a color of the xterm 256 color palette.
+pub fn palette(index: u8) -> [u8; 3] {
+    const BASIC: [[u8; 3]; 16] = [
+        [0, 0, 0], [205, 0, 0], [0, 205, 0], [205, 205, 0],
+        [0, 0, 238], [205, 0, 205], [0, 205, 205], [229, 229, 229],
+        [127, 127, 127], [255, 0, 0], [0, 255, 0], [255, 255, 0],
+        [92, 92, 255], [255, 0, 255], [0, 255, 255], [255, 255, 255],
+    ];
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match index {
+        0..=15 => BASIC[index as usize],
+        16..=231 => {
+            let index = index - 16;
+
+            [
+                CUBE[(index / 36) as usize],
+                CUBE[(index / 6 % 6) as usize],
+                CUBE[(index % 6) as usize],
+            ]
+        },
+        232..=255 => [8 + 10 * (index - 232); 3],
+    }
+}
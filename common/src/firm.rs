@@ -0,0 +1,147 @@
+//! Metadata that firmtool writes into the reserved bytes of a FIRM header,
+//! so a bootloader can pick between several images on the SD card.
+//!
+//! | offset | size | description                                     |
+//! |--------|------|-------------------------------------------------|
+//! | 0x00   | 4    | magic `SD3M`                                    |
+//! | 0x04   | 1    | layout version, currently 1                     |
+//! | 0x05   | 1    | target model: 0 any, 1 Old 3DS, 2 New 3DS       |
+//! | 0x06   | 2    | flags, bit 0 is set for a dirty working tree    |
+//! | 0x08   | 6    | build version as `major`, `minor`, `patch` u16s |
+//! | 0x0E   | 2    | zero                                            |
+//! | 0x10   | 20   | git commit hash                                 |
+//! | 0x24   | 12   | zero                                            |
+//!
+//! All fields are little endian.
+
+use core::convert::TryInto;
+use core::fmt;
+use core::str::FromStr;
+
+pub const HEADER_SIZE: usize = 0x200;
+pub const BOOT_PRIORITY_OFFSET: usize = 0x04;
+pub const RESERVED_OFFSET: usize = 0x10;
+pub const RESERVED_SIZE: usize = 0x30;
+
+pub const MAGIC: [u8; 4] = *b"SD3M";
+pub const LAYOUT_VERSION: u8 = 1;
+
+const FLAG_DIRTY: u16 = 1 << 0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TargetModel {
+    Any = 0,
+    O3ds = 1,
+    N3ds = 2,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub model: TargetModel,
+    pub version: Version,
+    pub git_hash: [u8; 20],
+    /// The image was built from a working tree with uncommitted changes
+    pub dirty: bool,
+}
+
+impl TargetModel {
+    fn from_u8(model: u8) -> Option<Self> {
+        match model {
+            0 => Some(TargetModel::Any),
+            1 => Some(TargetModel::O3ds),
+            2 => Some(TargetModel::N3ds),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = ();
+
+    /// Parses `major.minor.patch`, missing components are zero.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut parts = s.split('.').map(|part| part.parse::<u16>().map_err(|_| ()));
+        let version = Self {
+            major: parts.next().ok_or(())??,
+            minor: parts.next().transpose()?.unwrap_or(0),
+            patch: parts.next().transpose()?.unwrap_or(0),
+        };
+
+        match parts.next() {
+            Some(_) => Err(()),
+            None => Ok(version),
+        }
+    }
+}
+
+impl Metadata {
+    /// Reads the metadata from the reserved bytes of a header.
+    /// Returns `None` if they hold no metadata or an unknown layout.
+    pub fn from_bytes(reserved: &[u8]) -> Option<Self> {
+        let reserved: &[u8; RESERVED_SIZE] = reserved.get(..RESERVED_SIZE)?.try_into().ok()?;
+        let u16_at = |offset: usize| u16::from_le_bytes([reserved[offset], reserved[offset + 1]]);
+
+        if reserved[..4] != MAGIC || reserved[4] != LAYOUT_VERSION {
+            return None;
+        }
+
+        let mut git_hash = [0; 20];
+        git_hash.copy_from_slice(&reserved[0x10..0x24]);
+
+        Some(Self {
+            model: TargetModel::from_u8(reserved[5])?,
+            version: Version {
+                major: u16_at(0x08),
+                minor: u16_at(0x0A),
+                patch: u16_at(0x0C),
+            },
+            git_hash,
+            dirty: u16_at(0x06) & FLAG_DIRTY != 0,
+        })
+    }
+
+    /// Reads the metadata from a complete FIRM header.
+    pub fn from_header(header: &[u8]) -> Option<Self> {
+        Self::from_bytes(header.get(RESERVED_OFFSET..RESERVED_OFFSET + RESERVED_SIZE)?)
+    }
+
+    pub fn to_bytes(&self) -> [u8; RESERVED_SIZE] {
+        let mut reserved = [0; RESERVED_SIZE];
+        let flags = if self.dirty { FLAG_DIRTY } else { 0 };
+
+        reserved[..4].copy_from_slice(&MAGIC);
+        reserved[4] = LAYOUT_VERSION;
+        reserved[5] = self.model as u8;
+        reserved[0x06..0x08].copy_from_slice(&flags.to_le_bytes());
+        reserved[0x08..0x0A].copy_from_slice(&self.version.major.to_le_bytes());
+        reserved[0x0A..0x0C].copy_from_slice(&self.version.minor.to_le_bytes());
+        reserved[0x0C..0x0E].copy_from_slice(&self.version.patch.to_le_bytes());
+        reserved[0x10..0x24].copy_from_slice(&self.git_hash);
+
+        reserved
+    }
+
+    pub fn runs_on(&self, model: TargetModel) -> bool {
+        self.model == TargetModel::Any || self.model == model
+    }
+}
+
+/// Reads the boot priority from a complete FIRM header.
+pub fn boot_priority(header: &[u8]) -> Option<u32> {
+    let bytes = header.get(BOOT_PRIORITY_OFFSET..BOOT_PRIORITY_OFFSET + 4)?;
+
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
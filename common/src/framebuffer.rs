@@ -0,0 +1,403 @@
+//! Drawing into LCD framebuffers.
+//!
+//! The 3DS screens are mounted rotated, so their framebuffers are column-major:
+//! every column of the screen is one line in memory, starting at the bottom.
+//! [`Framebuffer`] hides this behind plain `x`/`y` coordinates with the origin
+//! in the top left corner.
+//! It also implements embedded-graphics' `DrawTarget` with `Rgb888` colors.
+
+use core::ops::Range;
+use core::ptr;
+
+mod graphics;
+
+/// Pixel formats of the LCD buffer format register (`set_buffer_format`),
+/// the discriminant is the value of its low 3 bits.
+///
+/// Names list components from the most to the least significant bits of a
+/// little endian pixel, so BGR8 is stored as R, G, B from the highest address down.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgba8 = 0,
+    Bgr8 = 1,
+    Rgb565 = 2,
+    Rgb5a1 = 3,
+    Rgba4 = 4,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    /// Row-major, starting at the top left corner
+    None,
+    /// Column-major, starting at the bottom left corner, as the 3DS LCDs scan out
+    Rotated90,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// A framebuffer over any byte buffer, `&mut [u8]` for the screens or a `Vec<u8>` on the host.
+/// Drawing requires a mutable buffer, reading and blitting from it only a shared one.
+///
+/// All drawing goes through volatile stores, since the LCD reads VRAM behind the compiler's back.
+pub struct Framebuffer<B> {
+    buf: B,
+    width: usize,
+    height: usize,
+    stride: usize,
+    format: PixelFormat,
+    rotation: Rotation,
+}
+
+impl PixelFormat {
+    pub fn from_buffer_format(value: u32) -> Option<Self> {
+        match value & 0b111 {
+            0 => Some(PixelFormat::Rgba8),
+            1 => Some(PixelFormat::Bgr8),
+            2 => Some(PixelFormat::Rgb565),
+            3 => Some(PixelFormat::Rgb5a1),
+            4 => Some(PixelFormat::Rgba4),
+            _ => None,
+        }
+    }
+
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::Bgr8 => 3,
+            PixelFormat::Rgb565 | PixelFormat::Rgb5a1 | PixelFormat::Rgba4 => 2,
+        }
+    }
+
+    pub const fn has_alpha(self) -> bool {
+        !matches!(self, PixelFormat::Bgr8 | PixelFormat::Rgb565)
+    }
+
+    /// Encodes a color into the first `bytes_per_pixel` bytes of the result.
+    pub fn encode(self, Color { r, g, b, a }: Color) -> [u8; 4] {
+        let bits = |c: u8, bits: u32| u16::from(c) >> (8 - bits);
+        let packed = |pixel: u16| {
+            let [lo, hi] = pixel.to_le_bytes();
+            [lo, hi, 0, 0]
+        };
+
+        match self {
+            PixelFormat::Rgba8 => [a, b, g, r],
+            PixelFormat::Bgr8 => [b, g, r, 0],
+            PixelFormat::Rgb565 => packed(bits(r, 5) << 11 | bits(g, 6) << 5 | bits(b, 5)),
+            PixelFormat::Rgb5a1 => packed(bits(r, 5) << 11 | bits(g, 5) << 6 | bits(b, 5) << 1 | bits(a, 1)),
+            PixelFormat::Rgba4 => packed(bits(r, 4) << 12 | bits(g, 4) << 8 | bits(b, 4) << 4 | bits(a, 4)),
+        }
+    }
+
+    /// Decodes the first `bytes_per_pixel` bytes of `pixel`.
+    pub fn decode(self, pixel: &[u8]) -> Color {
+        let packed = || u16::from_le_bytes([pixel[0], pixel[1]]);
+        // Repeat the high bits, so that all ones become 0xFF
+        let expand = |value: u16, shift: u32, bits: u32| {
+            let value = ((value >> shift) & ((1 << bits) - 1)) as u8;
+            let mut c = value << (8 - bits);
+            let mut filled = bits;
+
+            while filled < 8 {
+                c |= c >> filled;
+                filled *= 2;
+            }
+
+            c
+        };
+
+        match self {
+            PixelFormat::Rgba8 => Color::rgba(pixel[3], pixel[2], pixel[1], pixel[0]),
+            PixelFormat::Bgr8 => Color::rgb(pixel[2], pixel[1], pixel[0]),
+            PixelFormat::Rgb565 => {
+                let p = packed();
+                Color::rgb(expand(p, 11, 5), expand(p, 5, 6), expand(p, 0, 5))
+            },
+            PixelFormat::Rgb5a1 => {
+                let p = packed();
+                Color::rgba(expand(p, 11, 5), expand(p, 6, 5), expand(p, 1, 5), expand(p, 0, 1))
+            },
+            PixelFormat::Rgba4 => {
+                let p = packed();
+                Color::rgba(expand(p, 12, 4), expand(p, 8, 4), expand(p, 4, 4), expand(p, 0, 4))
+            },
+        }
+    }
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Color::rgb(r, g, b)
+    }
+}
+
+impl<B: AsRef<[u8]>> Framebuffer<B> {
+    /// Creates a framebuffer in the layout of the 3DS screens:
+    /// rotated by 90 degrees, with tightly packed columns.
+    ///
+    /// # Panics
+    /// Panics if `buf` is too small to hold the framebuffer.
+    pub fn new(buf: B, width: usize, height: usize, format: PixelFormat) -> Self {
+        Self::with_layout(buf, width, height, height * format.bytes_per_pixel(), format, Rotation::Rotated90)
+    }
+
+    /// Creates a framebuffer with an arbitrary layout.
+    /// `stride` is the distance in bytes between the starts of consecutive lines in memory,
+    /// which are columns for [`Rotation::Rotated90`] and rows otherwise.
+    ///
+    /// # Panics
+    /// Panics if `buf` is too small to hold the framebuffer,
+    /// or `stride` is smaller than a line.
+    pub fn with_layout(
+        buf: B,
+        width: usize,
+        height: usize,
+        stride: usize,
+        format: PixelFormat,
+        rotation: Rotation,
+    ) -> Self {
+        let fb = Self { buf, width, height, stride, format, rotation };
+        let (lines, line_len) = fb.lines();
+        let line_size = line_len * format.bytes_per_pixel();
+
+        assert!(stride >= line_size, "framebuffer stride is smaller than a line");
+
+        if lines > 0 && line_size > 0 {
+            let size = (lines - 1) * stride + line_size;
+            assert!(fb.buf.as_ref().len() >= size, "buffer is too small for the framebuffer");
+        }
+
+        fb
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf.as_ref()
+    }
+
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let pixel = &self.buf.as_ref()[self.offset(x, y)..];
+
+        Some(self.format.decode(pixel))
+    }
+
+    /// Number of lines in memory and their length in pixels.
+    fn lines(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::None => (self.height, self.width),
+            Rotation::Rotated90 => (self.width, self.height),
+        }
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        let bpp = self.format.bytes_per_pixel();
+
+        match self.rotation {
+            Rotation::None => y * self.stride + x * bpp,
+            Rotation::Rotated90 => x * self.stride + (self.height - 1 - y) * bpp,
+        }
+    }
+
+    /// Byte range of the `len` pixels that follow `(x, y)` along a line in memory,
+    /// rightwards for unrotated and downwards for rotated framebuffers.
+    fn span(&self, x: usize, y: usize, len: usize) -> Range<usize> {
+        let bpp = self.format.bytes_per_pixel();
+
+        match self.rotation {
+            Rotation::None => {
+                let start = self.offset(x, y);
+                start..start + len * bpp
+            },
+            Rotation::Rotated90 => {
+                let end = self.offset(x, y) + bpp;
+                end - len * bpp..end
+            },
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Framebuffer<B> {
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.buf.as_mut()
+    }
+
+    /// Sets a pixel, coordinates outside of the framebuffer are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let bpp = self.format.bytes_per_pixel();
+        let offset = self.offset(x, y);
+        let pixel = self.format.encode(color);
+
+        write_volatile(&mut self.buf.as_mut()[offset..offset + bpp], &pixel[..bpp]);
+    }
+
+    /// Fills a rectangle, clipped to the framebuffer.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        let x_end = x.saturating_add(width).min(self.width);
+        let y_end = y.saturating_add(height).min(self.height);
+
+        if x >= x_end || y >= y_end {
+            return;
+        }
+
+        let bpp = self.format.bytes_per_pixel();
+        let pixel = self.format.encode(color);
+        let pixel = &pixel[..bpp];
+
+        let lines = match self.rotation {
+            Rotation::None => y..y_end,
+            Rotation::Rotated90 => x..x_end,
+        };
+
+        for line in lines {
+            let span = match self.rotation {
+                Rotation::None => self.span(x, line, x_end - x),
+                Rotation::Rotated90 => self.span(line, y, y_end - y),
+            };
+
+            for dst in self.buf.as_mut()[span].chunks_exact_mut(bpp) {
+                write_volatile(dst, pixel);
+            }
+        }
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        self.fill_rect(0, 0, self.width, self.height, color);
+    }
+
+    /// Copies `src` to `(x, y)`, clipped to the framebuffer.
+    /// Pixels are converted if the formats differ, alpha is copied rather than blended.
+    pub fn blit<S: AsRef<[u8]>>(&mut self, x: usize, y: usize, src: &Framebuffer<S>) {
+        let width = src.width.min(self.width.saturating_sub(x));
+        let height = src.height.min(self.height.saturating_sub(y));
+
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        if src.format != self.format || src.rotation != self.rotation {
+            for src_y in 0..height {
+                for src_x in 0..width {
+                    let offset = src.offset(src_x, src_y);
+                    let color = src.format.decode(&src.buf.as_ref()[offset..]);
+
+                    self.set_pixel(x + src_x, y + src_y, color);
+                }
+            }
+
+            return;
+        }
+
+        // Same layout, copy whole lines
+        let src_buf = src.buf.as_ref();
+
+        match self.rotation {
+            Rotation::None => for line in 0..height {
+                let dst = self.span(x, y + line, width);
+                write_volatile(&mut self.buf.as_mut()[dst], &src_buf[src.span(0, line, width)]);
+            },
+            Rotation::Rotated90 => for line in 0..width {
+                let dst = self.span(x + line, y, height);
+                write_volatile(&mut self.buf.as_mut()[dst], &src_buf[src.span(line, 0, height)]);
+            },
+        }
+    }
+
+    /// Moves the content up by `lines` pixels and fills the rows that become free with `fill`.
+    pub fn scroll(&mut self, lines: usize, fill: Color) {
+        let lines = lines.min(self.height);
+        let kept = self.height - lines;
+
+        if kept > 0 {
+            match self.rotation {
+                Rotation::None => for y in 0..kept {
+                    let src = self.span(0, y + lines, self.width);
+                    let dst = self.span(0, y, self.width).start;
+                    copy_within_volatile(self.buf.as_mut(), src, dst);
+                },
+                Rotation::Rotated90 => for x in 0..self.width {
+                    let src = self.span(x, lines, kept);
+                    let dst = self.span(x, 0, kept).start;
+                    copy_within_volatile(self.buf.as_mut(), src, dst);
+                },
+            }
+        }
+
+        self.fill_rect(0, kept, self.width, lines, fill);
+    }
+}
+
+/// Copies `src` into `dst` with volatile stores, so that drawing is not optimised away
+/// even if nothing reads the buffer afterwards, like the panic screen before `loop {}`.
+fn write_volatile(dst: &mut [u8], src: &[u8]) {
+    assert_eq!(dst.len(), src.len());
+
+    for (dst, &src) in dst.iter_mut().zip(src) {
+        unsafe { ptr::write_volatile(dst, src) }
+    }
+}
+
+/// Like `<[u8]>::copy_within`, but with volatile stores.
+fn copy_within_volatile(buf: &mut [u8], src: Range<usize>, dst: usize) {
+    let len = src.len();
+    let mut copy = |i: usize| {
+        let byte = buf[src.start + i];
+        unsafe { ptr::write_volatile(&mut buf[dst + i], byte) }
+    };
+
+    // Copy away from the overlap, like memmove
+    if dst <= src.start {
+        (0..len).for_each(&mut copy);
+    } else {
+        (0..len).rev().for_each(&mut copy);
+    }
+}
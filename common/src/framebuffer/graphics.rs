@@ -0,0 +1,126 @@
+//! [`DrawTarget`] implementation, so that the embedded-graphics ecosystem can draw on the screens.
+
+use core::convert::{Infallible, TryFrom};
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::primitives::Rectangle;
+use super::{write_volatile, Color, Framebuffer, Rotation};
+
+impl From<Rgb888> for Color {
+    fn from(color: Rgb888) -> Self {
+        Color::rgb(color.r(), color.g(), color.b())
+    }
+}
+
+impl From<Color> for Rgb888 {
+    fn from(color: Color) -> Self {
+        Rgb888::new(color.r, color.g, color.b)
+    }
+}
+
+impl<B: AsRef<[u8]>> OriginDimensions for Framebuffer<B> {
+    fn size(&self) -> Size {
+        let saturate = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+
+        Size::new(saturate(self.width), saturate(self.height))
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> DrawTarget for Framebuffer<B> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<Rgb888>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) {
+                self.set_pixel(x, y, color.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Rgb888>,
+    {
+        let drawable = area.intersection(&self.bounding_box());
+        let bottom_right = match drawable.bottom_right() {
+            Some(bottom_right) => bottom_right,
+            None => return Ok(()),
+        };
+
+        // Colors are given row by row for the whole area, skip those of the clipped parts
+        let width = area.size.width as usize;
+        let visible = drawable.size.width as usize;
+        let left = (drawable.top_left.x - area.top_left.x) as usize;
+        let right = width - left - visible;
+        let top = (drawable.top_left.y - area.top_left.y) as usize;
+
+        let mut colors = colors.into_iter();
+        skip(&mut colors, top * width);
+
+        let bpp = self.format.bytes_per_pixel();
+        // Distance between horizontally adjacent pixels, a whole column for rotated framebuffers
+        let step = match self.rotation {
+            Rotation::None => bpp,
+            Rotation::Rotated90 => self.stride,
+        };
+        let x = drawable.top_left.x as usize;
+        let mut last = None;
+        let mut pixel = [0; 4];
+
+        for y in drawable.top_left.y as usize..=bottom_right.y as usize {
+            skip(&mut colors, left);
+
+            let start = self.offset(x, y);
+            let buf = self.buf.as_mut();
+
+            for offset in (start..).step_by(step).take(visible) {
+                let color = match colors.next() {
+                    Some(color) => color,
+                    None => return Ok(()),
+                };
+
+                if last != Some(color) {
+                    pixel = self.format.encode(color.into());
+                    last = Some(color);
+                }
+
+                write_volatile(&mut buf[offset..offset + bpp], &pixel[..bpp]);
+            }
+
+            skip(&mut colors, right);
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Rgb888) -> Result<(), Infallible> {
+        let area = area.intersection(&self.bounding_box());
+
+        if !area.is_zero_sized() {
+            let (x, y) = (area.top_left.x as usize, area.top_left.y as usize);
+            let size = area.size;
+
+            self.fill_rect(x, y, size.width as usize, size.height as usize, color.into());
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Rgb888) -> Result<(), Infallible> {
+        self.fill(color.into());
+
+        Ok(())
+    }
+}
+
+fn skip(iter: &mut impl Iterator, n: usize) {
+    if n > 0 {
+        iter.nth(n - 1);
+    }
+}
@@ -5,6 +5,12 @@ pub struct GamePad {
     current: PadState,
 }
 
+impl Default for GamePad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GamePad {
     pub fn new() -> GamePad {
         let state = PadState::read();
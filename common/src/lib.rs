@@ -6,24 +6,28 @@ pub mod mem;
 pub mod input;
 pub mod util;
 pub mod console;
+pub mod framebuffer;
+pub mod firm;
 
 pub use console::Console;
 
 #[cfg(all(feature="arm9", feature="arm11"))]
 compile_error!("arm9 and arm11 features are mutually exclusive");
 
+/// # Safety
+/// Zeroes `.bss`, so it must run once at startup before any static is touched.
 pub unsafe fn start() {
     clear_bss();
 }
 
 unsafe fn clear_bss() {
-    extern {
+    extern "C" {
         static mut __bss_start: u8;
         static mut __bss_end: u8;
     }
 
-    let bss_start = &mut __bss_start as *mut u8;
-    let bss_end = &mut __bss_end as *mut u8;
+    let bss_start = core::ptr::addr_of_mut!(__bss_start);
+    let bss_end = core::ptr::addr_of_mut!(__bss_end);
     let len = bss_end as usize - bss_start as usize;
 
     bss_start.write_bytes(0, len);
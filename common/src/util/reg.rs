@@ -7,10 +7,14 @@ impl<T> RW<T> {
         Self(addr as *mut T)
     }
 
+    /// # Safety
+    /// The address must be a valid, aligned register of type `T`.
     pub unsafe fn read(&self) -> T {
         read_volatile(self.0)
     }
 
+    /// # Safety
+    /// The address must be a valid, aligned register of type `T`.
     pub unsafe fn write(&mut self, t: T) {
         write_volatile(self.0, t)
     }
@@ -23,6 +27,8 @@ impl<T> RO<T> {
         Self(addr as *const T)
     }
 
+    /// # Safety
+    /// The address must be a valid, aligned register of type `T`.
     pub unsafe fn read(&self) -> T {
         read_volatile(self.0)
     }
@@ -35,6 +41,8 @@ impl<T> WO<T> {
         Self(addr as *mut T)
     }
 
+    /// # Safety
+    /// The address must be a valid, aligned register of type `T`.
     pub unsafe fn write(&mut self, t: T) {
         write_volatile(self.0, t)
     }
@@ -0,0 +1,136 @@
+use common::Console;
+use common::console::ansi::{Action, Parser};
+use common::framebuffer::{Color, Framebuffer, PixelFormat};
+
+#[derive(Debug, PartialEq)]
+enum Parsed {
+    Print(char),
+    Control(char),
+    Escape(char),
+    Csi(Vec<u16>, char),
+}
+
+fn parse(text: &str) -> Vec<Parsed> {
+    let mut parser = Parser::new();
+
+    text.chars()
+        .filter_map(|ch| parser.advance(ch))
+        .map(|action| match action {
+            Action::Print(ch) => Parsed::Print(ch),
+            Action::Control(ch) => Parsed::Control(ch),
+            Action::Escape(ch) => Parsed::Escape(ch),
+            Action::Csi(params, ch) => Parsed::Csi(params.as_slice().to_vec(), ch),
+        })
+        .collect()
+}
+
+/// Runs `f` on a console of `columns` by `rows` characters.
+fn with_console<T>(columns: usize, rows: usize, f: impl FnOnce(&mut Console) -> T) -> T {
+    let mut buf = vec![0; 3 * 64 * columns * rows];
+    let mut console = Console::new(Framebuffer::new(&mut buf[..], 8 * columns, 8 * rows, PixelFormat::Bgr8));
+
+    f(&mut console)
+}
+
+fn position_after(text: &str) -> (usize, usize) {
+    with_console(10, 3, |console| {
+        console.write_str(text);
+        console.position()
+    })
+}
+
+/// Color of a full block drawn after `text`.
+fn fg_after(text: &str) -> Color {
+    with_console(2, 1, |console| {
+        console.write_str(text);
+        console.write_str("\u{2588}");
+        console.framebuffer().pixel(0, 0).unwrap()
+    })
+}
+
+#[test]
+fn parses_csi_parameters() {
+    use Parsed::*;
+
+    assert_eq!(parse("\x1b[38;5;196m"), [Csi(vec![38, 5, 196], 'm')]);
+    assert_eq!(parse("\x1b[38;2;1;2;3m"), [Csi(vec![38, 2, 1, 2, 3], 'm')]);
+    assert_eq!(parse("\x1b[m"), [Csi(vec![0], 'm')]);
+    assert_eq!(parse("\x1b[;5H"), [Csi(vec![0, 5], 'H')]);
+    assert_eq!(parse("\x1b[99999A"), [Csi(vec![u16::MAX], 'A')]);
+    assert_eq!(parse("\x1b[1;2;3;4;5;6;7;8;9;10;11;12;13;14;15;16;17;18m"),
+        [Csi((1..=16).collect(), 'm')]);
+}
+
+#[test]
+fn parses_controls_and_escapes() {
+    use Parsed::*;
+
+    assert_eq!(parse("a\r\t\x08\n"), [Print('a'), Control('\r'), Control('\t'), Control('\x08'), Control('\n')]);
+    assert_eq!(parse("\x1b7\x1b8"), [Escape('7'), Escape('8')]);
+    // Private modes are consumed but ignored
+    assert_eq!(parse("\x1b[?25lx"), [Print('x')]);
+}
+
+#[test]
+fn escape_inside_csi_starts_a_new_sequence() {
+    use Parsed::*;
+
+    assert_eq!(parse("\x1b[31\x1b[32mX"), [Csi(vec![32], 'm'), Print('X')]);
+    assert_eq!(parse("\x1b[1\x1b7X"), [Escape('7'), Print('X')]);
+}
+
+#[test]
+fn sgr_colors() {
+    assert_eq!(fg_after(""), Color::WHITE);
+    assert_eq!(fg_after("\x1b[31m"), Color::rgb(205, 0, 0));
+    assert_eq!(fg_after("\x1b[92m"), Color::rgb(0, 255, 0));
+    assert_eq!(fg_after("\x1b[38;5;196m"), Color::rgb(255, 0, 0));
+    assert_eq!(fg_after("\x1b[38;5;244m"), Color::rgb(128, 128, 128));
+    assert_eq!(fg_after("\x1b[38;2;1;2;3m"), Color::rgb(1, 2, 3));
+    assert_eq!(fg_after("\x1b[38;2;1;2;3m\x1b[0m"), Color::WHITE);
+    assert_eq!(fg_after("\x1b[38;2;1;2;3m\x1b[39m"), Color::WHITE);
+    assert_eq!(fg_after("\x1b[44;7m"), Color::rgb(0, 0, 238));
+}
+
+#[test]
+fn malformed_sgr_parameters_are_ignored() {
+    assert_eq!(fg_after("\x1b[38;5m"), Color::WHITE);
+    assert_eq!(fg_after("\x1b[38;2;1;2m"), Color::WHITE);
+    assert_eq!(fg_after("\x1b[38m"), Color::WHITE);
+    // Components are clamped to a byte
+    assert_eq!(fg_after("\x1b[38;2;300;0;999m"), Color::rgb(255, 0, 255));
+    assert_eq!(fg_after("\x1b[38;5;300m"), Color::rgb(238, 238, 238));
+    // An unknown color space is skipped, later parameters still apply
+    assert_eq!(fg_after("\x1b[31;38;9;32m"), Color::rgb(0, 205, 0));
+}
+
+#[test]
+fn cursor_moves_are_clamped() {
+    assert_eq!(position_after("\x1b[3;5H"), (4, 2));
+    assert_eq!(position_after("\x1b[99;99H"), (9, 2));
+    assert_eq!(position_after("\x1b[0;0H"), (0, 0));
+    assert_eq!(position_after("\x1b[2;5H\x1b[99A"), (4, 0));
+    assert_eq!(position_after("\x1b[99B"), (0, 2));
+    assert_eq!(position_after("\x1b[99C"), (9, 0));
+    assert_eq!(position_after("\x1b[2;5H\x1b[99D"), (0, 1));
+    assert_eq!(position_after("abc\x1b[E"), (0, 1));
+    assert_eq!(position_after("\x1b[3;5H\x1b[F"), (0, 1));
+    assert_eq!(position_after("\x1b[7G"), (6, 0));
+    assert_eq!(position_after("\x1b[2;5H\x1b[C\x1b[B"), (5, 2));
+}
+
+#[test]
+fn cursor_is_saved_and_restored() {
+    assert_eq!(position_after("\x1b[2;4H\x1b[s\x1b[3;9Hxyz\x1b[u"), (3, 1));
+    assert_eq!(position_after("\x1b[2;4H\x1b7\x1b[H\x1b8"), (3, 1));
+}
+
+#[test]
+fn carriage_return_tab_and_backspace() {
+    assert_eq!(position_after("abc\r"), (0, 0));
+    assert_eq!(position_after("a\t"), (8, 0));
+    assert_eq!(position_after("a\t\t"), (10, 0));
+    assert_eq!(position_after("ab\x08"), (1, 0));
+    assert_eq!(position_after("\x08"), (0, 0));
+    assert_eq!(position_after("a\t\t\x08"), (9, 0));
+}
@@ -0,0 +1,58 @@
+use common::Console;
+use common::framebuffer::{Color, Framebuffer, PixelFormat};
+use font8x8::{UnicodeFonts, BASIC_FONTS, BOX_FONTS, GREEK_FONTS, LATIN_FONTS};
+
+/// Writes `text` to a console that is `cells` characters wide and returns the bitmap of every cell.
+fn render(text: &[u8], cells: usize) -> Vec<[u8; 8]> {
+    let mut buf = vec![0; 3 * 8 * cells * 8];
+    let mut console = Console::new(Framebuffer::new(&mut buf[..], 8 * cells, 8, PixelFormat::Bgr8));
+
+    console.write(text);
+
+    let fb = console.framebuffer();
+
+    (0..cells).map(|cell| {
+        let mut glyph = [0; 8];
+
+        for (y, row) in glyph.iter_mut().enumerate() {
+            for x in 0..8 {
+                if fb.pixel(8 * cell + x, y) == Some(Color::WHITE) {
+                    *row |= 1 << x;
+                }
+            }
+        }
+
+        glyph
+    }).collect()
+}
+
+fn font_glyph(ch: char) -> [u8; 8] {
+    BASIC_FONTS.get(ch)
+        .or_else(|| LATIN_FONTS.get(ch))
+        .or_else(|| GREEK_FONTS.get(ch))
+        .or_else(|| BOX_FONTS.get(ch))
+        .expect("glyph is in the font")
+}
+
+fn replacement() -> [u8; 8] {
+    font_glyph('?').map(|row| !row)
+}
+
+#[test]
+fn glyphs_come_from_the_unicode_tables() {
+    let text = "A─éλ";
+
+    assert_eq!(render(text.as_bytes(), 4), text.chars().map(font_glyph).collect::<Vec<_>>());
+}
+
+#[test]
+fn unmapped_chars_use_the_replacement_glyph() {
+    assert_eq!(render("→".as_bytes(), 1), [replacement()]);
+}
+
+#[test]
+fn invalid_utf8_uses_the_replacement_glyph() {
+    let glyphs = render(b"a\xFFb\xE2\x94", 4);
+
+    assert_eq!(glyphs, [font_glyph('a'), replacement(), font_glyph('b'), replacement()]);
+}
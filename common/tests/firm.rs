@@ -0,0 +1,70 @@
+use common::firm::{self, Metadata, TargetModel, Version};
+
+fn metadata() -> Metadata {
+    Metadata {
+        model: TargetModel::N3ds,
+        version: Version { major: 1, minor: 2, patch: 0x0304 },
+        git_hash: [0xAB; 20],
+        dirty: true,
+    }
+}
+
+#[test]
+fn metadata_round_trips() {
+    let metadata = metadata();
+
+    assert_eq!(Metadata::from_bytes(&metadata.to_bytes()), Some(metadata));
+
+    for &model in &[TargetModel::Any, TargetModel::O3ds, TargetModel::N3ds] {
+        let metadata = Metadata { model, dirty: false, ..metadata };
+        assert_eq!(Metadata::from_bytes(&metadata.to_bytes()), Some(metadata));
+    }
+}
+
+#[test]
+fn metadata_layout() {
+    let bytes = metadata().to_bytes();
+
+    assert_eq!(bytes.len(), firm::RESERVED_SIZE);
+    assert_eq!(bytes[..4], *b"SD3M");
+    assert_eq!(bytes[4], firm::LAYOUT_VERSION);
+    assert_eq!(bytes[5], 2);
+    assert_eq!(bytes[0x06..0x08], [1, 0]);
+    assert_eq!(bytes[0x08..0x0E], [1, 0, 2, 0, 4, 3]);
+    assert_eq!(bytes[0x10..0x24], [0xAB; 20]);
+    assert!(bytes[0x0E..0x10].iter().chain(&bytes[0x24..]).all(|&byte| byte == 0));
+}
+
+#[test]
+fn magic_and_version_are_checked() {
+    let mut bytes = metadata().to_bytes();
+    bytes[0] = b'X';
+    assert_eq!(Metadata::from_bytes(&bytes), None);
+
+    let mut bytes = metadata().to_bytes();
+    bytes[4] = firm::LAYOUT_VERSION + 1;
+    assert_eq!(Metadata::from_bytes(&bytes), None);
+
+    let mut bytes = metadata().to_bytes();
+    bytes[5] = 3;
+    assert_eq!(Metadata::from_bytes(&bytes), None);
+}
+
+#[test]
+fn zeroed_reserved_bytes_hold_no_metadata() {
+    assert_eq!(Metadata::from_bytes(&[0; firm::RESERVED_SIZE]), None);
+    assert_eq!(Metadata::from_header(&[0; firm::HEADER_SIZE]), None);
+    assert_eq!(Metadata::from_bytes(&metadata().to_bytes()[..firm::RESERVED_SIZE - 1]), None);
+}
+
+#[test]
+fn metadata_is_read_from_the_header() {
+    let mut header = [0; firm::HEADER_SIZE];
+    header[firm::BOOT_PRIORITY_OFFSET..][..4].copy_from_slice(&7u32.to_le_bytes());
+    header[firm::RESERVED_OFFSET..][..firm::RESERVED_SIZE].copy_from_slice(&metadata().to_bytes());
+
+    assert_eq!(Metadata::from_header(&header), Some(metadata()));
+    assert_eq!(firm::boot_priority(&header), Some(7));
+    assert!(metadata().runs_on(TargetModel::N3ds));
+    assert!(!metadata().runs_on(TargetModel::O3ds));
+}
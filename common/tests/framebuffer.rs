@@ -0,0 +1,122 @@
+use common::framebuffer::{Color, Framebuffer, PixelFormat, Rotation};
+use common::Console;
+use std::fmt::Write;
+
+const FORMATS: [PixelFormat; 5] = [
+    PixelFormat::Rgba8,
+    PixelFormat::Bgr8,
+    PixelFormat::Rgb565,
+    PixelFormat::Rgb5a1,
+    PixelFormat::Rgba4,
+];
+
+fn framebuffer(width: usize, height: usize, format: PixelFormat) -> Framebuffer<Vec<u8>> {
+    Framebuffer::new(vec![0; width * height * format.bytes_per_pixel()], width, height, format)
+}
+
+#[test]
+fn rotated_layout_is_column_major_from_the_bottom() {
+    let mut fb = framebuffer(4, 3, PixelFormat::Bgr8);
+
+    fb.set_pixel(0, 2, Color::rgb(1, 2, 3));
+    fb.set_pixel(1, 0, Color::rgb(4, 5, 6));
+
+    let bytes = fb.as_bytes();
+    assert_eq!(bytes[..3], [3, 2, 1]);
+    assert_eq!(bytes[9 + 6..9 + 9], [6, 5, 4]);
+}
+
+#[test]
+fn formats_round_trip() {
+    let colors = [Color::BLACK, Color::WHITE, Color::rgba(0xFF, 0, 0xFF, 0)];
+
+    for &format in &FORMATS {
+        for &color in &colors {
+            let expected = match format.has_alpha() {
+                true => color,
+                false => Color { a: 0xFF, ..color },
+            };
+
+            assert_eq!(format.decode(&format.encode(color)), expected, "{:?}", format);
+        }
+    }
+
+    assert_eq!(PixelFormat::Rgb565.encode(Color::rgb(0xFF, 0, 0))[..2], [0x00, 0xF8]);
+    assert_eq!(PixelFormat::Rgba8.encode(Color::rgba(1, 2, 3, 4)), [4, 3, 2, 1]);
+}
+
+#[test]
+fn fill_rect_is_clipped() {
+    for &rotation in &[Rotation::None, Rotation::Rotated90] {
+        let (width, height) = (5, 4);
+        let stride = 4 * match rotation {
+            Rotation::None => width,
+            Rotation::Rotated90 => height,
+        };
+        let buf = vec![0; stride * 5];
+        let mut fb = Framebuffer::with_layout(buf, width, height, stride, PixelFormat::Rgba8, rotation);
+
+        fb.fill_rect(3, 2, 10, 10, Color::WHITE);
+
+        for y in 0..height {
+            for x in 0..width {
+                let expected = if x >= 3 && y >= 2 { Color::WHITE } else { Color::default() };
+                assert_eq!(fb.pixel(x, y), Some(expected), "{:?} ({}, {})", rotation, x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn scroll_moves_content_up() {
+    for &rotation in &[Rotation::None, Rotation::Rotated90] {
+        let mut fb = Framebuffer::with_layout(vec![0; 3 * 4 * 4], 4, 4, 3 * 4, PixelFormat::Bgr8, rotation);
+
+        for y in 0..4 {
+            fb.fill_rect(0, y, 4, 1, Color::rgb(y as u8, 0, 0));
+        }
+
+        fb.scroll(1, Color::rgb(9, 9, 9));
+
+        for x in 0..4 {
+            let column: Vec<_> = (0..4).map(|y| fb.pixel(x, y).unwrap().r).collect();
+            assert_eq!(column, [1, 2, 3, 9], "{:?}", rotation);
+        }
+    }
+}
+
+#[test]
+fn blit_converts_and_clips() {
+    let mut src = Framebuffer::with_layout(vec![0; 2 * 2 * 2], 2, 2, 2 * 2, PixelFormat::Rgb565, Rotation::None);
+    src.set_pixel(0, 0, Color::WHITE);
+    src.set_pixel(1, 1, Color::rgb(0, 0xFF, 0));
+
+    let mut fb = framebuffer(3, 3, PixelFormat::Bgr8);
+    fb.blit(2, 1, &src);
+
+    assert_eq!(fb.pixel(2, 1), Some(Color::WHITE));
+    assert_eq!(fb.pixel(2, 2), Some(Color::BLACK));
+
+    let mut same = framebuffer(3, 3, PixelFormat::Rgb565);
+    let mut rotated_src = framebuffer(2, 2, PixelFormat::Rgb565);
+    rotated_src.blit(0, 0, &src);
+    same.blit(1, 1, &rotated_src);
+
+    assert_eq!(same.pixel(1, 1), Some(Color::WHITE));
+    assert_eq!(same.pixel(2, 2), Some(Color::rgb(0, 0xFF, 0)));
+    assert_eq!(same.pixel(0, 0), Some(Color::BLACK));
+}
+
+#[test]
+fn console_renders_ansi_colors() {
+    let mut buf = vec![0; 3 * 32 * 16];
+    let mut console = Console::new(Framebuffer::new(&mut buf[..], 32, 16, PixelFormat::Bgr8));
+
+    // A full block in truecolor, then a blue line erase on the second row
+    write!(console, "\x1b[38;2;1;2;3m\u{2588}\x1b[0m\x1b[2;1H\x1b[44m\x1b[K").unwrap();
+
+    let fb = console.framebuffer();
+    assert_eq!(fb.pixel(7, 7), Some(Color::rgb(1, 2, 3)));
+    assert_eq!(fb.pixel(8, 0), Some(Color::BLACK));
+    assert_eq!(fb.pixel(31, 8), Some(Color::rgb(0, 0, 238)));
+}
@@ -0,0 +1,101 @@
+use common::framebuffer::{Color, Framebuffer, PixelFormat, Rotation};
+use embedded_graphics::image::{Image, ImageRaw};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::Text;
+
+/// The bottom screen, in the rotated layout of the LCDs
+fn bottom_screen() -> Framebuffer<Vec<u8>> {
+    Framebuffer::new(vec![0; 3 * 320 * 240], 320, 240, PixelFormat::Bgr8)
+}
+
+/// Draws into both a rotated and an unrotated framebuffer and compares every pixel.
+fn assert_layouts_agree(draw: impl Fn(&mut Framebuffer<Vec<u8>>)) {
+    let mut rotated = Framebuffer::new(vec![0; 2 * 32 * 24], 32, 24, PixelFormat::Rgb565);
+    let mut unrotated = Framebuffer::with_layout(vec![0; 2 * 32 * 24], 32, 24, 2 * 32, PixelFormat::Rgb565, Rotation::None);
+
+    draw(&mut rotated);
+    draw(&mut unrotated);
+
+    for y in 0..24 {
+        for x in 0..32 {
+            assert_eq!(rotated.pixel(x, y), unrotated.pixel(x, y), "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn size_matches_the_screen() {
+    assert_eq!(bottom_screen().size(), Size::new(320, 240));
+}
+
+#[test]
+fn fill_solid_is_clipped() {
+    let mut fb = bottom_screen();
+
+    Rectangle::new(Point::new(-5, 230), Size::new(10, 20))
+        .into_styled(PrimitiveStyle::with_fill(Rgb888::RED))
+        .draw(&mut fb)
+        .unwrap();
+
+    assert_eq!(fb.pixel(0, 239), Some(Color::rgb(255, 0, 0)));
+    assert_eq!(fb.pixel(4, 230), Some(Color::rgb(255, 0, 0)));
+    assert_eq!(fb.pixel(5, 230), Some(Color::BLACK));
+    assert_eq!(fb.pixel(0, 229), Some(Color::BLACK));
+}
+
+#[test]
+fn fill_contiguous_keeps_colors_in_row_order() {
+    let mut fb = bottom_screen();
+    let colors = (0..12).map(|n| Rgb888::new(n, 0, 0));
+
+    // Partially off screen to the top left, so the first row and column are skipped
+    fb.fill_contiguous(&Rectangle::new(Point::new(-1, -1), Size::new(4, 3)), colors).unwrap();
+
+    assert_eq!(fb.pixel(0, 0), Some(Color::rgb(5, 0, 0)));
+    assert_eq!(fb.pixel(2, 0), Some(Color::rgb(7, 0, 0)));
+    assert_eq!(fb.pixel(0, 1), Some(Color::rgb(9, 0, 0)));
+    assert_eq!(fb.pixel(3, 0), Some(Color::BLACK));
+}
+
+#[test]
+fn text_and_images_draw_alike_in_both_layouts() {
+    assert_layouts_agree(|fb| {
+        fb.clear(Rgb888::BLUE).unwrap();
+
+        Text::new("3DS", Point::new(2, 10), MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE))
+            .draw(fb)
+            .unwrap();
+
+        let data: Vec<u8> = (0..4 * 4 * 3).map(|n| n as u8 * 5).collect();
+        let raw = ImageRaw::<Rgb888>::new(&data, 4);
+        Image::new(&raw, Point::new(28, 20)).draw(fb).unwrap();
+    });
+}
+
+#[test]
+fn fill_contiguous_stops_after_the_last_visible_row() {
+    let mut fb = bottom_screen();
+    let mut consumed = 0;
+    let colors = (0..16).map(|n| Rgb888::new(n, 0, 0)).inspect(|_| consumed += 1);
+
+    fb.fill_contiguous(&Rectangle::new(Point::new(318, 238), Size::new(4, 4)), colors).unwrap();
+
+    assert_eq!(fb.pixel(318, 238), Some(Color::rgb(0, 0, 0)));
+    assert_eq!(fb.pixel(319, 238), Some(Color::rgb(1, 0, 0)));
+    assert_eq!(fb.pixel(318, 239), Some(Color::rgb(4, 0, 0)));
+    assert_eq!(fb.pixel(319, 239), Some(Color::rgb(5, 0, 0)));
+    assert!(consumed <= 8, "consumed {} colors", consumed);
+}
+
+#[test]
+fn fill_contiguous_draws_alike_in_both_layouts() {
+    assert_layouts_agree(|fb| {
+        for &(x, y) in &[(-3, -2), (20, 15), (-10, 18), (28, -5)] {
+            let colors = (0..).map(|n: u32| Rgb888::new((n * 7) as u8, (n * 13) as u8, (n * 29) as u8));
+            fb.fill_contiguous(&Rectangle::new(Point::new(x, y), Size::new(13, 11)), colors).unwrap();
+        }
+    });
+}
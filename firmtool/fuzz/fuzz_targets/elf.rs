@@ -0,0 +1,11 @@
+//! cargo fuzz run elf corpus/elf ../tests/malformed/elf
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use firmtool::elf::extract_program;
+use firmtool::symbols::SymbolTable;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = extract_program(data);
+    let _ = SymbolTable::from_elf(data);
+});
@@ -0,0 +1,17 @@
+//! cargo fuzz run firm corpus/firm ../tests/malformed/firm
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use firmtool::Firm;
+use firmtool::hashes::ContentHashes;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(firm) = Firm::parse(data) {
+        for (i, _) in firm.section_headers() {
+            let _ = firm.section_data(i);
+        }
+
+        let _ = firm.header().signature();
+        let _ = ContentHashes::new(&firm);
+    }
+});
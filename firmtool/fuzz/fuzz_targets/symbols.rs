@@ -0,0 +1,12 @@
+//! cargo fuzz run symbols corpus/symbols ../tests/malformed/symbols
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use firmtool::symbols::SymbolTable;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(table) = SymbolTable::from_bytes(data) {
+        let _ = table.lookup(0x0800_0000);
+        let _ = SymbolTable::from_bytes(&table.to_bytes());
+    }
+});
@@ -1,21 +1,86 @@
 use std::io::Write;
+use std::ops::Range;
 use std::convert::TryFrom;
 use sha2::{Sha256, Digest};
-use crate::{Result, CopyMethod, signature};
-use crate::header::{self, Header, SectionHeader};
+use crate::{Result, FirmError, CopyMethod, Cpu, Firm, Signature};
+use crate::header::{self, Header, Metadata, SectionHeader};
+use crate::memmap::{self, Issue, Model};
 
-#[derive(Default)]
 pub struct Builder {
+    boot_priority: u32,
     arm9_entrypoint: Option<u32>,
     arm11_entrypoint: Option<u32>,
+    signature: Signature,
+    reserved: [u8; 0x30],
+    model: Option<Model>,
+    sort_by_address: bool,
     sections: Vec<Section>,
 }
 
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            boot_priority: 0,
+            arm9_entrypoint: None,
+            arm11_entrypoint: None,
+            signature: Signature::NandRetail,
+            reserved: [0; 0x30],
+            model: None,
+            sort_by_address: false,
+            sections: Vec::new(),
+        }
+    }
+}
+
 impl Builder {
     pub fn new() -> Self {
         Builder::default()
     }
 
+    /// Starts from an existing image, keeping its priority, entrypoints,
+    /// signature, reserved bytes and sections in their original order.
+    /// Empty section slots are dropped, so indices only count used slots.
+    pub fn from_firm(firm: &Firm) -> Result<Self> {
+        let header = firm.header();
+        let mut builder = Builder::new();
+
+        builder
+            .boot_priority(header.boot_priority)
+            .arm9_entrypoint(header.arm9_entrypoint)
+            .arm11_entrypoint(Some(header.arm11_entrypoint).filter(|&entrypoint| entrypoint != 0))
+            .signature(header.signature());
+        builder.reserved = header.reserved;
+
+        for (i, section) in firm.section_headers() {
+            let data = firm.section_data(i).ok_or(FirmError::SectionOutsideImage(i))?;
+            builder.add_section(Section::new(section.addr, section.copy_method, data.to_vec())?);
+        }
+
+        Ok(builder)
+    }
+
+    pub fn boot_priority(&mut self, priority: u32) -> &mut Self {
+        self.boot_priority = priority;
+        self
+    }
+
+    pub fn signature(&mut self, signature: Signature) -> &mut Self {
+        self.signature = signature;
+        self
+    }
+
+    /// Stores a metadata block in the otherwise zeroed reserved bytes of the header.
+    pub fn metadata(&mut self, metadata: impl Into<Option<Metadata>>) -> &mut Self {
+        self.reserved = metadata.into().map(|metadata| metadata.to_bytes()).unwrap_or([0; 0x30]);
+        self
+    }
+
+    /// Validates the memory map against the given model before writing.
+    pub fn model(&mut self, model: impl Into<Option<Model>>) -> &mut Self {
+        self.model = model.into();
+        self
+    }
+
     pub fn arm9_entrypoint(&mut self, entry: impl Into<Option<u32>>) -> &mut Self {
         self.arm9_entrypoint = entry.into();
         self
@@ -31,57 +96,167 @@ impl Builder {
         self
     }
 
-    pub fn build(&mut self) -> Result<Vec<u8>> {
-        let mut firm = Vec::new();
+    /// Sections in insertion order.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
 
-        self.write_to(&mut firm)?;
+    /// Replaces the section at `index` with zero or more new sections.
+    pub fn replace_section(&mut self, index: usize, replacement: impl IntoIterator<Item = Section>) -> Result<&mut Self> {
+        if index >= self.sections.len() {
+            return Err(FirmError::NoSuchSection(index));
+        }
 
-        Ok(firm)
+        self.sections.splice(index..=index, replacement);
+
+        Ok(self)
     }
 
-    pub fn write_to<W: Write>(&mut self, w: &mut W) -> Result {
-        if self.sections.len() > 4 {
-            Err("Firm cannot contain more than fore sections")?
+    /// Orders the sections by load address instead of insertion order.
+    pub fn sort_by_address(&mut self, sort: bool) -> &mut Self {
+        self.sort_by_address = sort;
+        self
+    }
+
+    /// Sections in the order they will appear in the image.
+    fn ordered_sections(&self) -> Vec<&Section> {
+        let mut sections = self.sections.iter().collect::<Vec<_>>();
+
+        if self.sort_by_address {
+            sections.sort_by_key(|section| section.addr);
         }
 
-        let arm9_entrypoint = self.arm9_entrypoint.ok_or("Missing arm9 entrypoint")?;
-        let mut arm9_entrypoint_found = false;
+        sections
+    }
 
-        for section in &self.sections {
-            arm9_entrypoint_found |= section.contains_addr(arm9_entrypoint);
+    /// Computes where each section will be placed in the image
+    /// and makes sure no two sections overlap in memory.
+    pub fn layout(&self) -> Result<Vec<SectionLayout>> {
+        if self.sections.len() > 4 {
+            return Err(FirmError::TooManySections(self.sections.len()));
         }
 
-        if !arm9_entrypoint_found {
-            Err("arm9 entry does not point into any section")?
+        let sections = self.ordered_sections();
+
+        for (i, a) in sections.iter().enumerate() {
+            for (j, b) in sections.iter().enumerate().skip(i + 1) {
+                if a.overlaps(b) {
+                    return Err(FirmError::SectionOverlap {
+                        first: i,
+                        first_range: a.range(),
+                        second: j,
+                        second_range: b.range(),
+                    });
+                }
+            }
         }
 
         let mut offset = header::SIZE;
-        let mut section_headers = <[SectionHeader; 4]>::default();
+        let mut layout = Vec::new();
 
-        for (header, section) in section_headers.iter_mut().zip(&self.sections) {            
-            *header = SectionHeader {
+        for (index, section) in sections.into_iter().enumerate() {
+            layout.push(SectionLayout {
+                index,
                 offset,
                 addr: section.addr,
                 size: section.size(),
                 copy_method: section.copy_method,
                 sha256: Sha256::digest(&section.data).into(),
-            };
+            });
 
             offset += section.size();
         }
 
+        Ok(layout)
+    }
+
+    /// Checks the sections and entrypoints against the memory map of `model`.
+    /// Issues are paired with the index of the offending section, if any.
+    pub fn check_memory_map(&self, model: Model) -> Vec<(Option<usize>, Issue)> {
+        let mut issues = Vec::new();
+
+        for (i, section) in self.ordered_sections().into_iter().enumerate() {
+            for issue in memmap::check_section(model, section.addr, section.size(), section.copy_method) {
+                issues.push((Some(i), issue));
+            }
+        }
+
+        let entrypoints = [
+            (Cpu::Arm9, self.arm9_entrypoint),
+            (Cpu::Arm11, self.arm11_entrypoint),
+        ];
+
+        for &(cpu, entrypoint) in &entrypoints {
+            if let Some(issue) = entrypoint.and_then(|entrypoint| memmap::check_entrypoint(cpu, entrypoint)) {
+                issues.push((None, issue));
+            }
+        }
+
+        issues
+    }
+
+    pub fn build(&mut self) -> Result<Vec<u8>> {
+        let mut firm = Vec::new();
+
+        self.write_to(&mut firm)?;
+
+        Ok(firm)
+    }
+
+    pub fn write_to<W: Write>(&mut self, w: &mut W) -> Result {
+        let layout = self.layout()?;
+        let sections = self.ordered_sections();
+
+        let arm9_entrypoint = self.arm9_entrypoint.ok_or(FirmError::MissingEntrypoint(Cpu::Arm9))?;
+        let entrypoints = [
+            (Cpu::Arm9, Some(arm9_entrypoint)),
+            (Cpu::Arm11, self.arm11_entrypoint),
+        ];
+
+        for &(cpu, entrypoint) in &entrypoints {
+            let entrypoint = match entrypoint {
+                Some(entrypoint) => entrypoint,
+                None => continue,
+            };
+
+            if !self.sections.iter().any(|section| section.contains_addr(entrypoint)) {
+                return Err(FirmError::EntrypointOutsideSections { cpu, entrypoint });
+            }
+        }
+
+        if let Some(model) = self.model {
+            let error = self.check_memory_map(model).into_iter()
+                .find(|(_, issue)| issue.is_error());
+
+            if let Some((section, issue)) = error {
+                return Err(FirmError::MemoryMap { section, issue });
+            }
+        }
+
+        let mut section_headers = <[SectionHeader; 4]>::default();
+
+        for (header, layout) in section_headers.iter_mut().zip(&layout) {
+            *header = SectionHeader {
+                offset: layout.offset,
+                addr: layout.addr,
+                size: layout.size,
+                copy_method: layout.copy_method,
+                sha256: layout.sha256,
+            };
+        }
+
         let header = Header {
-            boot_priority: 0,
+            boot_priority: self.boot_priority,
             arm9_entrypoint,
             arm11_entrypoint: self.arm11_entrypoint.unwrap_or(0),
             section_headers,
-            reserved: [0; 0x30],
-            rsa_signature: signature::NAND_RETAIL,
+            reserved: self.reserved,
+            rsa_signature: *self.signature.bytes(),
         };
 
         header.to_writer(w)?;
 
-        for section in &self.sections {
+        for section in sections {
             w.write_all(&section.data)?;
         }
 
@@ -89,22 +264,56 @@ impl Builder {
     }
 }
 
+/// Placement of a section within the image and the address space.
+#[derive(Clone, Debug)]
+pub struct SectionLayout {
+    pub index: usize,
+    pub offset: u32,
+    pub addr: u32,
+    pub size: u32,
+    pub copy_method: CopyMethod,
+    pub sha256: [u8; 32],
+}
+
 pub struct Section {
     addr: u32,
     copy_method: CopyMethod,
     data: Vec<u8>,
 }
 
+/// Size granularity that section data is padded to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Alignment {
+    /// eMMC sectors (512 bytes)
+    #[default]
+    Sector,
+    /// AES blocks (16 bytes)
+    Block,
+}
+
+impl Alignment {
+    pub fn size(self) -> usize {
+        match self {
+            Alignment::Sector => 512,
+            Alignment::Block => 16,
+        }
+    }
+}
+
 impl Section {
-    pub fn new(addr: u32, copy_method: CopyMethod, mut data: Vec<u8>) -> Result<Section> {
-        // Align size to eMMC sectors (512 bytes)
-        while data.len() % 512 != 0 {
-            data.push(0xFF);
-        }        
+    /// Creates a section padded to 512 bytes with `0xFF`.
+    pub fn new(addr: u32, copy_method: CopyMethod, data: Vec<u8>) -> Result<Section> {
+        Self::with_padding(addr, copy_method, data, Alignment::Sector, 0xFF)
+    }
 
-        let size = u32::try_from(data.len())?;
+    pub fn with_padding(addr: u32, copy_method: CopyMethod, mut data: Vec<u8>, alignment: Alignment, fill: u8) -> Result<Section> {
+        while !data.len().is_multiple_of(alignment.size()) {
+            data.push(fill);
+        }
 
-        addr.checked_add(size).ok_or("Section lies outside of address space")?;
+        u32::try_from(data.len()).ok()
+            .and_then(|size| addr.checked_add(size))
+            .ok_or(FirmError::SectionOverflow { addr, size: data.len() as u64 })?;
 
         Ok(Self { addr, copy_method, data })
     }
@@ -113,6 +322,10 @@ impl Section {
         self.addr
     }
 
+    pub fn copy_method(&self) -> CopyMethod {
+        self.copy_method
+    }
+
     pub fn contains_addr(&self, addr: u32) ->bool {
         let len = self.data.len() as u32;
 
@@ -122,4 +335,13 @@ impl Section {
     pub fn size(&self) -> u32 {
         self.data.len() as u32
     }
-}
\ No newline at end of file
+
+    /// The memory range the section is loaded to.
+    pub fn range(&self) -> Range<u32> {
+        self.addr .. self.addr + self.size()
+    }
+
+    pub fn overlaps(&self, other: &Section) -> bool {
+        self.addr < other.range().end && other.addr < self.range().end
+    }
+}
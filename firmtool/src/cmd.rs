@@ -0,0 +1,11 @@
+pub mod build;
+pub mod parse;
+pub mod verify;
+pub mod extract;
+pub mod info;
+pub mod symbolize;
+pub mod diff;
+pub mod sdimage;
+pub mod check_manifest;
+pub mod image;
+pub mod patch;
@@ -0,0 +1,388 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use firmtool::{Builder, Section, CopyMethod, Cpu, Firm};
+use firmtool::hashes::ContentHashes;
+use firmtool::header::{Metadata, TargetModel, Version};
+use firmtool::elf::extract_program;
+use firmtool::symbols::SymbolTable;
+use crate::Result;
+use crate::manifest::{Manifest, MetadataSpec, SectionSpec, SignatureName, ModelName};
+use crate::util::{self, parse_u32, parse_u8};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Where to write the FIRM image, overrides the output of the manifest
+    #[arg(short, long, required_unless_present = "manifest")]
+    output: Option<PathBuf>,
+    /// Build the image described by a TOML manifest instead of the options below
+    #[arg(long, value_name = "PATH", conflicts_with_all = [
+        "arm9", "arm11", "arm9_copy_method", "arm11_copy_method", "arm9_entry", "arm11_entry",
+        "priority", "signature", "signature_file", "sort", "o3ds", "n3ds", "raw",
+        "symbol_maps", "embed_symbols", "align", "fill", "reproducible",
+        "metadata", "git_hash",
+    ])]
+    manifest: Option<PathBuf>,
+    /// ELF file to load for the ARM9
+    #[arg(long)]
+    arm9: Option<PathBuf>,
+    /// ELF file to load for the ARM11
+    #[arg(long)]
+    arm11: Option<PathBuf>,
+    /// Copy method for the ARM9 sections
+    #[arg(long, default_value = "NDMA")]
+    arm9_copy_method: CopyMethod,
+    /// Copy method for the ARM11 sections
+    #[arg(long, default_value = "XDMA")]
+    arm11_copy_method: CopyMethod,
+    /// Overrides the ARM9 entrypoint taken from the ELF file
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32)]
+    arm9_entry: Option<u32>,
+    /// Overrides the ARM11 entrypoint taken from the ELF file
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32)]
+    arm11_entry: Option<u32>,
+    /// Boot priority written to the header
+    #[arg(long, default_value_t = 0)]
+    priority: u32,
+    /// Signature to embed in the header
+    #[arg(long, value_enum, default_value = "nand-retail")]
+    signature: SignatureName,
+    /// Embeds a custom 0x100 byte signature instead, e.g. the SPI or dev unit sighax blobs
+    #[arg(long, value_name = "PATH", conflicts_with = "signature")]
+    signature_file: Option<PathBuf>,
+    /// Order sections by load address instead of command line order
+    #[arg(long)]
+    sort: bool,
+    /// Print the section layout before writing the image
+    #[arg(long)]
+    print_layout: bool,
+    /// Validate load addresses against the Old 3DS memory map (default)
+    #[arg(long, conflicts_with = "n3ds")]
+    o3ds: bool,
+    /// Validate load addresses against the New 3DS memory map
+    #[arg(long)]
+    n3ds: bool,
+    /// Adds a raw binary as an extra section, copied with NDMA unless specified
+    #[arg(long, value_name = "ADDR:PATH[:COPY_METHOD]")]
+    raw: Vec<RawSection>,
+    /// Write an address to symbol table for each ELF file into this directory
+    #[arg(long, value_name = "DIR")]
+    symbol_maps: Option<PathBuf>,
+    /// Embed the symbol tables of all ELF files as an extra section at this address
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32)]
+    embed_symbols: Option<u32>,
+    /// Pad the size of every section to this many bytes
+    #[arg(long, value_enum, default_value = "512")]
+    align: AlignArg,
+    /// Byte used to pad sections, 0xFF unless the build is reproducible
+    #[arg(long, value_name = "BYTE", value_parser = parse_u8)]
+    fill: Option<u8>,
+    /// Write a metadata block with this build version into the reserved header bytes
+    #[arg(long, value_name = "VERSION")]
+    metadata: Option<String>,
+    /// Commit hash for the metadata block, defaults to the HEAD of the current git repository
+    #[arg(long, value_name = "HASH", requires = "metadata")]
+    git_hash: Option<String>,
+    /// Pad sections with zeros unless --fill is given and write the SHA-256
+    /// of the image and each section to <OUTPUT>.sha256
+    #[arg(long, alias = "hashes")]
+    reproducible: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum AlignArg {
+    #[value(name = "512")]
+    Sector,
+    #[value(name = "16")]
+    Block,
+}
+
+#[derive(Clone)]
+struct RawSection {
+    addr: u32,
+    path: PathBuf,
+    copy_method: CopyMethod,
+}
+
+impl FromStr for RawSection {
+    type Err = String;
+
+    /// The address ends at the first colon, a copy method at the last one.
+    /// Everything in between is the path, which may contain colons itself.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, rest) = s.split_once(':')
+            .ok_or_else(|| format!("Invalid raw section '{}', expected ADDR:PATH[:COPY_METHOD]", s))?;
+        let (path, copy_method) = match rest.rsplit_once(':') {
+            Some((path, copy_method)) => match copy_method.parse() {
+                Ok(copy_method) => (path, copy_method),
+                Err(_) => (rest, CopyMethod::NDMA),
+            },
+            None => (rest, CopyMethod::NDMA),
+        };
+
+        Ok(Self {
+            addr: parse_u32(addr)?,
+            path: path.into(),
+            copy_method,
+        })
+    }
+}
+
+pub fn run(args: Args) -> Result {
+    let manifest = match &args.manifest {
+        Some(path) => Manifest::load(path)?,
+        None => args.to_manifest(),
+    };
+    let output = args.output.as_ref()
+        .or(manifest.output.as_ref())
+        .ok_or("The manifest does not specify an output path, pass one with --output")?;
+
+    let mut builder = build(&manifest)?;
+
+    if args.print_layout {
+        println!("section  offset      address     size        copy method");
+
+        for section in builder.layout()? {
+            println!("{:<8} 0x{:08X}  0x{:08X}  0x{:08X}  {:?}",
+                section.index, section.offset, section.addr, section.size, section.copy_method,
+            );
+        }
+    }
+
+    let firm = builder.build()?;
+    util::write(output, &firm)?;
+
+    if manifest.reproducible {
+        let hashes = ContentHashes::new(&Firm::parse(&firm)?)?;
+        let mut path = output.clone().into_os_string();
+        path.push(".sha256");
+
+        util::write(Path::new(&path), hashes.to_string().as_bytes())?;
+    }
+
+    Ok(())
+}
+
+impl Args {
+    fn to_manifest(&self) -> Manifest {
+        let elf = |path: &PathBuf, copy_method, cpu: Cpu| SectionSpec {
+            elf: Some(path.clone()),
+            raw: None,
+            addr: None,
+            copy_method: Some(copy_method),
+            entry: Some(cpu.into()),
+        };
+        let arm9 = self.arm9.iter().map(|path| elf(path, self.arm9_copy_method, Cpu::Arm9));
+        let arm11 = self.arm11.iter().map(|path| elf(path, self.arm11_copy_method, Cpu::Arm11));
+        let raw = self.raw.iter().map(|raw| SectionSpec {
+            elf: None,
+            raw: Some(raw.path.clone()),
+            addr: Some(raw.addr),
+            copy_method: Some(raw.copy_method),
+            entry: None,
+        });
+
+        Manifest {
+            output: self.output.clone(),
+            priority: self.priority,
+            signature: self.signature,
+            signature_file: self.signature_file.clone(),
+            model: match (self.o3ds, self.n3ds) {
+                (_, true) => Some(ModelName::N3ds),
+                (true, _) => Some(ModelName::O3ds),
+                _ => None,
+            },
+            sort: self.sort,
+            align: match self.align {
+                AlignArg::Sector => 512,
+                AlignArg::Block => 16,
+            },
+            fill: self.fill,
+            reproducible: self.reproducible,
+            arm9_entry: self.arm9_entry,
+            arm11_entry: self.arm11_entry,
+            symbol_maps: self.symbol_maps.clone(),
+            embed_symbols: self.embed_symbols,
+            metadata: self.metadata.as_ref().map(|version| MetadataSpec {
+                version: version.clone(),
+                git_hash: self.git_hash.clone(),
+            }),
+            sections: arm9.chain(arm11).chain(raw).collect(),
+            base_dir: PathBuf::new(),
+        }
+    }
+}
+
+/// Sets up a builder for the image described by `manifest`.
+fn build(manifest: &Manifest) -> Result<Builder> {
+    let mut builder = Builder::new();
+    let alignment = manifest.alignment()?;
+    let fill = manifest.fill();
+    let new_section = |addr, copy_method, data| {
+        Section::with_padding(addr, copy_method, data, alignment, fill)
+    };
+
+    builder.boot_priority(manifest.priority);
+    builder.signature(manifest.signature()?);
+
+    if let Some(spec) = &manifest.metadata {
+        builder.metadata(metadata(spec, manifest.target_model(), &manifest.base_dir)?);
+    }
+
+    let mut all_symbols = SymbolTable::default();
+    let mut collect_symbols = |name: &str, elf: &[u8]| -> Result {
+        if manifest.symbol_maps.is_none() && manifest.embed_symbols.is_none() {
+            return Ok(());
+        }
+
+        let symbols = SymbolTable::from_elf(elf)?;
+
+        if let Some(dir) = &manifest.symbol_maps {
+            fs::create_dir_all(dir)?;
+            util::write(&dir.join(format!("{}.sym", name)), &symbols.to_bytes())?;
+        }
+
+        all_symbols.extend(symbols);
+
+        Ok(())
+    };
+
+    for spec in &manifest.sections {
+        match (&spec.elf, &spec.raw) {
+            (Some(path), None) => {
+                if spec.addr.is_some() {
+                    return Err(format!("{}: ELF sections are loaded to the address in the file", path.display()).into());
+                }
+
+                let elf = util::read(path)?;
+                let program = extract_program(&elf)
+                    .map_err(|err| format!("Failed to load {}: {}", path.display(), err))?;
+                let cpu = spec.entry.map(|cpu| cpu.cpu());
+                let copy_method = spec.copy_method.unwrap_or(match cpu {
+                    Some(Cpu::Arm11) => CopyMethod::XDMA,
+                    _ => CopyMethod::NDMA,
+                });
+
+                match cpu {
+                    Some(Cpu::Arm9) => builder.arm9_entrypoint(program.entrypoint),
+                    Some(Cpu::Arm11) => builder.arm11_entrypoint(program.entrypoint),
+                    None => &mut builder,
+                };
+
+                collect_symbols(&symbol_map_name(path, cpu), &elf)?;
+
+                for segment in program.segments {
+                    let section = new_section(segment.addr, copy_method, segment.data)?;
+                    builder.add_section(section);
+                }
+            }
+            (None, Some(path)) => {
+                if spec.entry.is_some() {
+                    return Err(format!("{}: only ELF sections can provide an entrypoint", path.display()).into());
+                }
+
+                let addr = spec.addr
+                    .ok_or_else(|| format!("{}: raw sections need a load address", path.display()))?;
+                let data = util::read(path)?;
+                let section = new_section(addr, spec.copy_method.unwrap_or_default(), data)?;
+                builder.add_section(section);
+            }
+            _ => return Err("Every section needs exactly one of 'elf' or 'raw'".into()),
+        }
+    }
+
+    if let Some(addr) = manifest.embed_symbols {
+        let section = new_section(addr, CopyMethod::NDMA, all_symbols.to_bytes())?;
+        builder.add_section(section);
+    }
+
+    if let Some(entry) = manifest.arm9_entry {
+        builder.arm9_entrypoint(entry);
+    }
+
+    if let Some(entry) = manifest.arm11_entry {
+        builder.arm11_entrypoint(entry);
+    }
+
+    // Warnings number the sections the same way as the layout
+    builder.sort_by_address(manifest.sort);
+
+    let model = manifest.model();
+
+    for (section, issue) in builder.check_memory_map(model) {
+        if issue.is_error() {
+            continue;
+        }
+
+        match section {
+            Some(index) => eprintln!("warning: section {}: {}", index, issue),
+            None => eprintln!("warning: {}", issue),
+        }
+    }
+
+    builder.model(model);
+
+    Ok(builder)
+}
+
+fn metadata(spec: &MetadataSpec, model: TargetModel, base_dir: &Path) -> Result<Metadata> {
+    let version = spec.version.parse::<Version>()
+        .map_err(|()| format!("Invalid build version '{}', expected MAJOR.MINOR.PATCH", spec.version))?;
+    let (git_hash, dirty) = match &spec.git_hash {
+        Some(hash) => (hash.clone(), false),
+        None => {
+            let hash = git(base_dir, &["rev-parse", "HEAD"])?;
+            let dirty = !git(base_dir, &["status", "--porcelain", "--untracked-files=no"])?.is_empty();
+
+            (hash, dirty)
+        }
+    };
+    let git_hash = parse_git_hash(&git_hash)
+        .ok_or_else(|| format!("Invalid git hash '{}', expected 40 hex digits", git_hash))?;
+
+    Ok(Metadata {
+        model,
+        version,
+        git_hash,
+        dirty,
+    })
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let output = Command::new("git")
+        .arg("-C").arg(dir)
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to run git, set the git hash explicitly: {}", err))?;
+
+    if !output.status.success() {
+        return Err(format!("git {} failed, set the git hash explicitly: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn parse_git_hash(hash: &str) -> Option<[u8; 20]> {
+    let mut bytes = [0; 20];
+
+    if hash.len() != 40 || !hash.is_ascii() {
+        return None;
+    }
+
+    for (byte, digits) in bytes.iter_mut().zip(hash.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+
+    Some(bytes)
+}
+
+/// Symbol maps are named after the CPU an ELF file is the entrypoint for,
+/// otherwise after the file itself.
+fn symbol_map_name(path: &Path, cpu: Option<Cpu>) -> String {
+    match cpu {
+        Some(cpu) => cpu.to_string(),
+        None => path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+    }
+}
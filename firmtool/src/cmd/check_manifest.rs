@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use firmtool::Firm;
+use firmtool::hashes::ContentHashes;
+use crate::Result;
+use crate::cmd::verify::Report;
+use crate::util::{self, hex};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// FIRM image to check
+    firm: PathBuf,
+    /// Content hashes written by a reproducible build, defaults to <FIRM>.sha256
+    hashes: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result {
+    let hashes_path = match args.hashes {
+        Some(path) => path,
+        None => {
+            let mut path = args.firm.clone().into_os_string();
+            path.push(".sha256");
+            path.into()
+        }
+    };
+    let expected = String::from_utf8(util::read(&hashes_path)?)?;
+    let expected = expected.parse::<ContentHashes>()
+        .map_err(|err| format!("{}: {}", hashes_path.display(), err))?;
+    let firm = util::read(&args.firm)?;
+    let actual = ContentHashes::new(&Firm::parse(&firm)?)?;
+    let mut report = Report::default();
+
+    for section in &expected.sections {
+        let found = actual.sections.iter().find(|other| other.index == section.index);
+
+        report.check(found.is_some(), format!("section {} is present", section.index));
+
+        let found = match found {
+            Some(found) => found,
+            None => continue,
+        };
+
+        report.check((found.addr, found.size) == (section.addr, section.size), format!(
+            "section {} is loaded to 0x{:08X}+0x{:X}", section.index, section.addr, section.size,
+        ));
+        report.check(found.sha256 == section.sha256, format!(
+            "section {} sha256 matches ({})", section.index, hex(&section.sha256),
+        ));
+
+        if found.sha256 != section.sha256 {
+            report.note(&format!("got {}", hex(&found.sha256)));
+        }
+    }
+
+    for section in &actual.sections {
+        if !expected.sections.iter().any(|other| other.index == section.index) {
+            report.check(false, format!("section {} is listed in {}", section.index, hashes_path.display()));
+        }
+    }
+
+    report.check(actual.firm == expected.firm, format!("image sha256 matches ({})", hex(&expected.firm)));
+
+    if actual.firm != expected.firm {
+        report.note(&format!("got {}", hex(&actual.firm)));
+    }
+
+    if report.failed > 0 {
+        Err(format!("{} check(s) failed", report.failed))?
+    }
+
+    println!("image matches {}", hashes_path.display());
+
+    Ok(())
+}
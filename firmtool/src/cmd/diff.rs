@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+use std::fmt::{self, Display};
+use std::cmp::min;
+use std::convert::TryFrom;
+use firmtool::{Firm, Signature};
+use firmtool::header::SectionHeader;
+use firmtool::symbols::SymbolTable;
+use crate::Result;
+use crate::util::{self, hex};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The known good image
+    old: PathBuf,
+    /// The image to compare against it
+    new: PathBuf,
+    /// ELF files of the new image, to map changed bytes to symbols
+    #[arg(long)]
+    elf: Vec<PathBuf>,
+    /// Maximum number of changed byte ranges to list per section
+    #[arg(long, default_value_t = 20)]
+    max_ranges: usize,
+}
+
+pub fn run(args: Args) -> Result {
+    let old = util::read(&args.old)?;
+    let old = Firm::parse(&old)?;
+    let new = util::read(&args.new)?;
+    let new = Firm::parse(&new)?;
+    let mut symbols = SymbolTable::default();
+
+    for path in &args.elf {
+        let file = util::read(path)?;
+        let table = SymbolTable::from_elf(&file)
+            .map_err(|err| format!("Failed to load {}: {}", path.display(), err))?;
+        symbols.extend(table);
+    }
+
+    let mut differences = 0;
+    let (a, b) = (old.header(), new.header());
+
+    differences += field("boot priority", &a.boot_priority, &b.boot_priority);
+    differences += field("arm9 entrypoint", &Hex(a.arm9_entrypoint), &Hex(b.arm9_entrypoint));
+    differences += field("arm11 entrypoint", &Hex(a.arm11_entrypoint), &Hex(b.arm11_entrypoint));
+    differences += field("reserved", &hex(&a.reserved), &hex(&b.reserved));
+    differences += field("signature", &ShortSignature(a.signature()), &ShortSignature(b.signature()));
+
+    for (i, (a, b)) in a.section_headers.iter().zip(&b.section_headers).enumerate() {
+        differences += section(i, a, b);
+
+        if a.is_empty() || b.is_empty() || a.sha256 == b.sha256 {
+            continue;
+        }
+
+        let (old_data, new_data) = match (old.section_data(i), new.section_data(i)) {
+            (Some(old_data), Some(new_data)) => (old_data, new_data),
+            _ => {
+                println!("  section {} lies outside of the file", i);
+                continue;
+            },
+        };
+
+        if a.addr != b.addr {
+            println!("  section {} moved, not comparing contents", i);
+            continue;
+        }
+
+        print_changed_ranges(b.addr, old_data, new_data, &symbols, args.max_ranges);
+    }
+
+    if differences == 0 {
+        println!("images are identical");
+    }
+
+    Ok(())
+}
+
+#[derive(PartialEq)]
+struct Hex(u32);
+
+impl Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:08X}", self.0)
+    }
+}
+
+/// Known signatures by name, custom ones by their first bytes.
+#[derive(PartialEq)]
+struct ShortSignature(Signature);
+
+impl Display for ShortSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Signature::Custom(bytes) => write!(f, "custom ({}...)", hex(&bytes[..8])),
+            signature => write!(f, "{}", signature),
+        }
+    }
+}
+
+fn field<T: PartialEq + Display>(name: &str, a: &T, b: &T) -> usize {
+    if a == b {
+        return 0;
+    }
+
+    println!("{}: {} -> {}", name, a, b);
+
+    1
+}
+
+fn section(index: usize, a: &SectionHeader, b: &SectionHeader) -> usize {
+    let prefix = format!("section {} ", index);
+
+    field(&(prefix.clone() + "offset"), &Hex(a.offset), &Hex(b.offset))
+    + field(&(prefix.clone() + "address"), &Hex(a.addr), &Hex(b.addr))
+    + field(&(prefix.clone() + "size"), &Hex(a.size), &Hex(b.size))
+    + field(&(prefix.clone() + "copy method"), &format!("{:?}", a.copy_method), &format!("{:?}", b.copy_method))
+    + field(&(prefix + "sha256"), &hex(&a.sha256), &hex(&b.sha256))
+}
+
+/// Lists runs of differing bytes, including bytes only present in one of the sections.
+fn print_changed_ranges(addr: u32, old: &[u8], new: &[u8], symbols: &SymbolTable, max_ranges: usize) {
+    let len = min(old.len(), new.len());
+    let mut ranges = Vec::new();
+    let mut start = None;
+
+    for offset in 0 .. len {
+        match (old[offset] != new[offset], start) {
+            (true, None) => start = Some(offset),
+            (false, Some(range_start)) => {
+                ranges.push(range_start .. offset);
+                start = None;
+            },
+            _ => {},
+        }
+    }
+
+    if let Some(range_start) = start {
+        ranges.push(range_start .. len);
+    }
+
+    if old.len() != new.len() {
+        ranges.push(len .. old.len().max(new.len()));
+    }
+
+    for range in ranges.iter().take(max_ranges) {
+        // Sections of malformed images may run past the end of the address space
+        let start = u64::from(addr) + range.start as u64;
+        let end = u64::from(addr) + range.end as u64;
+        let symbol = match u32::try_from(start).ok().and_then(|start| symbols.lookup(start)) {
+            Some((symbol, 0)) => symbol.name.clone(),
+            Some((symbol, offset)) => format!("{}+0x{:X}", symbol.name, offset),
+            None => "??".into(),
+        };
+
+        println!("  0x{:08X}..0x{:08X} ({} bytes) {}", start, end, range.len(), symbol);
+    }
+
+    if ranges.len() > max_ranges {
+        println!("  ... and {} more", ranges.len() - max_ranges);
+    }
+}
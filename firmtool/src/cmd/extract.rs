@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::PathBuf;
+use firmtool::Firm;
+use crate::Result;
+use crate::util;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// FIRM image to read
+    firm: PathBuf,
+    /// Directory to write the sections to
+    out_dir: PathBuf,
+    /// Also wrap each section into an ELF file
+    #[arg(long)]
+    elf: bool,
+}
+
+pub fn run(args: Args) -> Result {
+    let firm = util::read(&args.firm)?;
+    let firm = Firm::parse(&firm)?;
+    let header = firm.header();
+
+    fs::create_dir_all(&args.out_dir)?;
+
+    for (i, section) in firm.section_headers() {
+        let data = firm.section_data(i)
+            .ok_or_else(|| format!("Section {} lies outside of the file", i))?;
+        let path = args.out_dir.join(format!("section{}.bin", i));
+
+        util::write(&path, data)?;
+        println!("{}", path.display());
+
+        if !args.elf {
+            continue;
+        }
+
+        let entrypoint = [header.arm9_entrypoint, header.arm11_entrypoint].iter().copied()
+            .find(|&entrypoint| section.contains_addr(entrypoint))
+            .unwrap_or(section.addr);
+        let path = args.out_dir.join(format!("section{}.elf", i));
+        let mut elf = Vec::new();
+
+        firmtool::elf::write_executable(&mut elf, section.addr, entrypoint, data)?;
+        util::write(&path, &elf)?;
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
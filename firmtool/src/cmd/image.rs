@@ -0,0 +1,116 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use png::{ColorType, Transformations};
+use common::framebuffer::{Color, Framebuffer, PixelFormat};
+use crate::Result;
+use crate::util;
+
+/// Both screens are 240 pixels high, the framebuffer is rotated by 90 degrees
+const SCREEN_HEIGHT: usize = 240;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// PNG image with the exact size of the screen
+    png: PathBuf,
+    /// Where to write the raw framebuffer data
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Screen the image is meant for, 400x240 for the top and 320x240 for the bottom screen
+    #[arg(long, value_enum, default_value = "top")]
+    screen: Screen,
+    /// Pixel format of the framebuffer
+    #[arg(long, value_enum, default_value = "bgr8")]
+    format: Format,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum Screen {
+    Top,
+    Bottom,
+}
+
+/// Framebuffer formats as selected by the low bits of the LCD buffer format register.
+/// Names follow the GPU, bytes are stored little endian (BGR8 is B, G, R in memory).
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum Format {
+    Rgba8,
+    Bgr8,
+    Rgb565,
+    Rgb5a1,
+    Rgba4,
+}
+
+impl Screen {
+    fn width(self) -> usize {
+        match self {
+            Screen::Top => 400,
+            Screen::Bottom => 320,
+        }
+    }
+}
+
+impl From<Format> for PixelFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Rgba8 => PixelFormat::Rgba8,
+            Format::Bgr8 => PixelFormat::Bgr8,
+            Format::Rgb565 => PixelFormat::Rgb565,
+            Format::Rgb5a1 => PixelFormat::Rgb5a1,
+            Format::Rgba4 => PixelFormat::Rgba4,
+        }
+    }
+}
+
+pub fn run(args: Args) -> Result {
+    let file = File::open(&args.png)
+        .map_err(|err| format!("Failed to read {}: {}", args.png.display(), err))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    if (width, height) != (args.screen.width(), SCREEN_HEIGHT) {
+        return Err(format!("{} is {}x{}, the screen is {}x{}",
+            args.png.display(), width, height, args.screen.width(), SCREEN_HEIGHT,
+        ).into());
+    }
+
+    let rgba = |x: usize, y: usize| -> [u8; 4] {
+        let row = &pixels[y * info.line_size..];
+
+        match info.color_type {
+            ColorType::Rgba => [row[4 * x], row[4 * x + 1], row[4 * x + 2], row[4 * x + 3]],
+            ColorType::Rgb => [row[3 * x], row[3 * x + 1], row[3 * x + 2], 0xFF],
+            ColorType::GrayscaleAlpha => [row[2 * x], row[2 * x], row[2 * x], row[2 * x + 1]],
+            // Palettes are expanded to RGB(A) by the decoder
+            ColorType::Grayscale | ColorType::Indexed => [row[x], row[x], row[x], 0xFF],
+        }
+    };
+
+    let format = PixelFormat::from(args.format);
+    let mut framebuffer = Framebuffer::new(vec![0; width * height * format.bytes_per_pixel()], width, height, format);
+
+    for x in 0..width {
+        for y in 0..height {
+            let [r, g, b, a] = rgba(x, y);
+            let color = match format.has_alpha() {
+                true => Color::rgba(r, g, b, a),
+                // Blend translucent pixels onto black for formats without alpha
+                false => {
+                    let blend = |c: u8| (u16::from(c) * u16::from(a) / 0xFF) as u8;
+                    Color::rgb(blend(r), blend(g), blend(b))
+                }
+            };
+
+            framebuffer.set_pixel(x, y, color);
+        }
+    }
+
+    util::write(&args.output, framebuffer.as_bytes())?;
+
+    Ok(())
+}
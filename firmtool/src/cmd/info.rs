@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use std::cmp::Reverse;
+use firmtool::Firm;
+use firmtool::memmap;
+use firmtool::symbols::SymbolTable;
+use crate::Result;
+use crate::util;
+
+/// The top screen framebuffer as set up by the ARM11 payload
+const TOP_FRAMEBUFFER: std::ops::Range<u64> = 0x18000000 .. 0x18000000 + 3 * 400 * 240;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// FIRM image to describe
+    firm: PathBuf,
+    /// Print the memory map of all sections
+    #[arg(long)]
+    map: bool,
+    /// ELF files the image was built from, to list their largest symbols
+    #[arg(long)]
+    elf: Vec<PathBuf>,
+    /// Number of symbols to list per ELF file
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+pub fn run(args: Args) -> Result {
+    let firm = util::read(&args.firm)?;
+    let firm = Firm::parse(&firm)?;
+    let header = firm.header();
+
+    println!("image size:       0x{:08X}", firm.data().len());
+    println!("sections:         {}", firm.section_headers().count());
+    println!("arm9 entrypoint:  0x{:08X}", header.arm9_entrypoint);
+    println!("arm11 entrypoint: 0x{:08X}", header.arm11_entrypoint);
+    println!("signature:        {}", header.signature());
+
+    if args.map {
+        println!();
+        print_map(&firm);
+    }
+
+    for path in &args.elf {
+        println!();
+        print_symbols(path, args.top)?;
+    }
+
+    Ok(())
+}
+
+fn print_map(firm: &Firm) {
+    println!("section  address range           size        copy  region                 fill");
+
+    for (i, section) in firm.section_headers() {
+        let start = u64::from(section.addr);
+        let end = start + u64::from(section.size);
+        let region = memmap::region_of(section.addr)
+            .filter(|region| end <= region.range.end);
+        let (region_name, fill) = match &region {
+            Some(region) => {
+                let region_size = region.range.end - region.range.start;
+                (region.name, format!("{:6.2}%", 100.0 * section.size as f64 / region_size as f64))
+            },
+            None => ("-", "-".into()),
+        };
+
+        println!("{:<8} 0x{:08X}..0x{:08X}  0x{:08X}  {:<4}  {:<22} {}",
+            i, start, end, section.size, format!("{:?}", section.copy_method), region_name, fill,
+        );
+
+        if start < TOP_FRAMEBUFFER.end && TOP_FRAMEBUFFER.start < end {
+            println!("         warning: collides with the top framebuffer at 0x{:08X}", TOP_FRAMEBUFFER.start);
+        }
+    }
+
+    // Several sections may share a region, so report the combined usage as well
+    for region in memmap::regions() {
+        let used = firm.section_headers()
+            .filter(|(_, section)| region.range.contains(&u64::from(section.addr)))
+            .map(|(_, section)| u64::from(section.size))
+            .sum::<u64>();
+
+        if used == 0 {
+            continue;
+        }
+
+        let region_size = region.range.end - region.range.start;
+
+        println!("{:<22} 0x{:08X} of 0x{:08X} bytes used ({:.2}%)",
+            region.name, used, region_size, 100.0 * used as f64 / region_size as f64,
+        );
+    }
+}
+
+fn print_symbols(path: &Path, top: usize) -> Result {
+    let file = util::read(path)?;
+    let symbols = SymbolTable::from_elf(&file)
+        .map_err(|err| format!("Failed to load {}: {}", path.display(), err))?;
+    let mut symbols = symbols.symbols().iter().collect::<Vec<_>>();
+
+    symbols.sort_by_key(|symbol| Reverse(symbol.size));
+
+    println!("largest symbols in {}:", path.display());
+
+    for symbol in symbols.into_iter().take(top) {
+        println!("  0x{:08X}  0x{:08X}  {}", symbol.addr, symbol.size, symbol.name);
+    }
+
+    Ok(())
+}
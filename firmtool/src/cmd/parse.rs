@@ -0,0 +1,44 @@
+use std::path::Path;
+use firmtool::Firm;
+use crate::Result;
+use crate::util::{self, hex};
+
+pub fn run(path: &Path) -> Result {
+    let firm = util::read(path)?;
+    let firm = Firm::parse(&firm)?;
+    let header = firm.header();
+
+    println!("boot priority:    {}", header.boot_priority);
+    println!("arm9 entrypoint:  0x{:08X}", header.arm9_entrypoint);
+    println!("arm11 entrypoint: 0x{:08X}", header.arm11_entrypoint);
+
+    for (i, section) in header.section_headers.iter().enumerate() {
+        println!("section {}:", i);
+
+        if section.is_empty() {
+            println!("  (empty)");
+            continue;
+        }
+
+        println!("  offset:      0x{:08X}", section.offset);
+        println!("  address:     0x{:08X}", section.addr);
+        println!("  size:        0x{:08X}", section.size);
+        println!("  copy method: {:?}", section.copy_method);
+        println!("  sha256:      {}", hex(&section.sha256));
+    }
+
+    println!("signature:        {}", header.signature());
+
+    match header.metadata() {
+        Some(metadata) => {
+            println!("metadata:");
+            println!("  version:     {}", metadata.version);
+            println!("  git hash:    {}{}", hex(&metadata.git_hash), if metadata.dirty { " (dirty)" } else { "" });
+            println!("  model:       {:?}", metadata.model);
+        }
+        None if header.reserved.iter().any(|&byte| byte != 0) => println!("reserved:         {}", hex(&header.reserved)),
+        None => {}
+    }
+
+    Ok(())
+}
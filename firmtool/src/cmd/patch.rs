@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+use firmtool::{Builder, CopyMethod, Firm, Section};
+use firmtool::elf::extract_program;
+use crate::Result;
+use crate::util::{self, parse_u32};
+
+#[derive(clap::Args)]
+#[command(group = clap::ArgGroup::new("target").required(true))]
+#[command(group = clap::ArgGroup::new("payload").required(true))]
+pub struct Args {
+    /// FIRM image to patch
+    firm: PathBuf,
+    /// Where to write the patched image, defaults to patching the image in place
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Replace the section in this header slot
+    #[arg(long, value_name = "INDEX", group = "target")]
+    section: Option<usize>,
+    /// Replace the section that is loaded to this address
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32, group = "target")]
+    at: Option<u32>,
+    /// ELF file to load instead, entrypoints into the old section follow the new one
+    #[arg(long, group = "payload")]
+    elf: Option<PathBuf>,
+    /// Raw binary to load instead
+    #[arg(long, group = "payload")]
+    raw: Option<PathBuf>,
+    /// Load address of the raw binary, defaults to the one of the replaced section
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32, requires = "raw")]
+    load_addr: Option<u32>,
+    /// Copy method of the new section, defaults to the one of the replaced section
+    #[arg(long)]
+    copy_method: Option<CopyMethod>,
+    /// Overrides the ARM9 entrypoint
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32)]
+    arm9_entry: Option<u32>,
+    /// Overrides the ARM11 entrypoint
+    #[arg(long, value_name = "ADDR", value_parser = parse_u32)]
+    arm11_entry: Option<u32>,
+}
+
+pub fn run(args: Args) -> Result {
+    let data = util::read(&args.firm)?;
+    let firm = Firm::parse(&data)?;
+    let header = firm.header();
+    let mut builder = Builder::from_firm(&firm)?;
+
+    // The builder only knows the used slots, in header order
+    let used = firm.section_headers().collect::<Vec<_>>();
+    let index = match (args.section, args.at) {
+        (Some(slot), _) => used.iter().position(|&(i, _)| i == slot)
+            .ok_or_else(|| format!("Section {} is empty", slot))?,
+        (None, Some(addr)) => used.iter().position(|(_, section)| section.contains_addr(addr))
+            .ok_or_else(|| format!("No section is loaded to 0x{:08X}", addr))?,
+        (None, None) => unreachable!("clap requires a target"),
+    };
+    let (slot, old) = used[index];
+    let copy_method = args.copy_method.unwrap_or(old.copy_method);
+    let mut replacement = Vec::new();
+
+    if let Some(path) = &args.elf {
+        let elf = util::read(path)?;
+        let program = extract_program(&elf)
+            .map_err(|err| format!("Failed to load {}: {}", path.display(), err))?;
+
+        if old.contains_addr(header.arm9_entrypoint) {
+            builder.arm9_entrypoint(program.entrypoint);
+        }
+
+        if header.arm11_entrypoint != 0 && old.contains_addr(header.arm11_entrypoint) {
+            builder.arm11_entrypoint(program.entrypoint);
+        }
+
+        for segment in program.segments {
+            replacement.push(Section::new(segment.addr, copy_method, segment.data)?);
+        }
+    }
+
+    if let Some(path) = &args.raw {
+        let data = util::read(path)?;
+        replacement.push(Section::new(args.load_addr.unwrap_or(old.addr), copy_method, data)?);
+    }
+
+    println!("replacing section {} (0x{:08X}+0x{:X} {:?}) with", slot, old.addr, old.size, old.copy_method);
+
+    for section in &replacement {
+        println!("  0x{:08X}+0x{:X} {:?}", section.addr(), section.size(), section.copy_method());
+    }
+
+    builder.replace_section(index, replacement)?;
+
+    if let Some(entry) = args.arm9_entry {
+        builder.arm9_entrypoint(entry);
+    }
+
+    if let Some(entry) = args.arm11_entry {
+        builder.arm11_entrypoint(entry);
+    }
+
+    let patched = builder.build()?;
+    util::write(args.output.as_ref().unwrap_or(&args.firm), &patched)?;
+
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use std::cmp;
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use fatfs::{FatType, FileSystem, FormatVolumeOptions, FsOptions, ReadWriteSeek};
+use firmtool::Firm;
+use crate::Result;
+use crate::util;
+
+const SECTOR_SIZE: u64 = 512;
+/// The partition starts at 1 MiB like on factory formatted cards
+const PARTITION_START: u32 = 2048;
+/// Smallest image that still has enough clusters for FAT32
+const MIN_SIZE_MIB: u64 = 34;
+/// Largest image whose partition size fits into the 32-bit sector counts of the MBR and FAT32
+const MAX_SIZE_MIB: u64 = 2 * 1024 * 1024;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// FIRM image to install as boot.firm
+    firm: PathBuf,
+    /// Where to write the disk image
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Size of the disk image in MiB
+    #[arg(long, default_value_t = 64)]
+    size: u64,
+    /// Volume label of the FAT32 partition
+    #[arg(long, default_value = "3DS")]
+    label: String,
+    /// Extra file or directory to copy onto the card, to the root unless a destination is given
+    #[arg(long, value_name = "PATH[:DEST]")]
+    add: Vec<ExtraFile>,
+}
+
+#[derive(Clone)]
+struct ExtraFile {
+    path: PathBuf,
+    dest: Option<String>,
+}
+
+impl FromStr for ExtraFile {
+    type Err = String;
+
+    /// The destination follows the last colon, so the host path may contain colons itself.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, dest) = match s.rsplit_once(':') {
+            Some((path, dest)) => (path, Some(dest.trim_matches('/').to_string())),
+            None => (s, None),
+        };
+
+        Ok(Self {
+            path: path.into(),
+            dest,
+        })
+    }
+}
+
+pub fn run(args: Args) -> Result {
+    let firm = util::read(&args.firm)?;
+    Firm::parse(&firm)
+        .map_err(|err| format!("{} is not a valid FIRM image: {}", args.firm.display(), err))?;
+
+    if args.size < MIN_SIZE_MIB {
+        return Err(format!("FAT32 needs an image of at least {} MiB", MIN_SIZE_MIB).into());
+    }
+
+    if args.size > MAX_SIZE_MIB {
+        return Err(format!("FAT32 images in an MBR partition cannot exceed {} MiB", MAX_SIZE_MIB).into());
+    }
+
+    let total_sectors = args.size * 1024 * 1024 / SECTOR_SIZE;
+    let partition_sectors = u32::try_from(total_sectors - u64::from(PARTITION_START))
+        .map_err(|_| format!("An image of {} MiB has too many sectors for FAT32", args.size))?;
+    let label = volume_label(&args.label)?;
+
+    let mut image = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&args.output)
+        .map_err(|err| format!("Failed to create {}: {}", args.output.display(), err))?;
+    image.set_len(total_sectors * SECTOR_SIZE)?;
+    write_mbr(&mut image, PARTITION_START, partition_sectors)?;
+
+    let mut partition = Partition::new(
+        &mut image,
+        u64::from(PARTITION_START) * SECTOR_SIZE,
+        u64::from(partition_sectors) * SECTOR_SIZE,
+    );
+    let options = FormatVolumeOptions::new()
+        .fat_type(FatType::Fat32)
+        .total_sectors(partition_sectors)
+        .volume_label(label);
+    fatfs::format_volume(&mut partition, options)?;
+
+    let volume = FileSystem::new(partition, FsOptions::new())?;
+
+    {
+        let root = volume.root_dir();
+
+        root.create_file("boot.firm")?.write_all(&firm)?;
+
+        for extra in &args.add {
+            let name = extra.path.file_name()
+                .ok_or_else(|| format!("Cannot add {}", extra.path.display()))?
+                .to_string_lossy();
+            let dest = extra.dest.clone().unwrap_or_else(|| name.into_owned());
+
+            copy(&root, &extra.path, &dest)?;
+        }
+    }
+
+    let stats = volume.stats()?;
+    volume.unmount()?;
+
+    println!("{}: {} MiB, {} KiB free",
+        args.output.display(), args.size,
+        u64::from(stats.free_clusters()) * u64::from(stats.cluster_size()) / 1024,
+    );
+
+    Ok(())
+}
+
+/// Copies a file or a whole directory tree from the host to `dest` on the card.
+fn copy<T: ReadWriteSeek>(root: &fatfs::Dir<T>, path: &Path, dest: &str) -> Result {
+    if path.is_dir() {
+        if !dest.is_empty() {
+            create_dir_all(root, dest)?;
+        }
+
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let dest = match dest {
+                "" => name.to_string_lossy().into_owned(),
+                dest => format!("{}/{}", dest, name.to_string_lossy()),
+            };
+
+            copy(root, &entry.path(), &dest)?;
+        }
+    } else {
+        if let Some((dir, _)) = dest.rsplit_once('/') {
+            create_dir_all(root, dir)?;
+        }
+
+        let data = util::read(path)?;
+        root.create_file(dest)
+            .and_then(|mut file| {
+                file.truncate()?;
+                file.write_all(&data)
+            })
+            .map_err(|err| format!("Failed to write {} to the image: {}", dest, err))?;
+    }
+
+    Ok(())
+}
+
+fn create_dir_all<T: ReadWriteSeek>(root: &fatfs::Dir<T>, path: &str) -> Result {
+    let mut dir = root.clone();
+
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        dir = dir.create_dir(name)?;
+    }
+
+    Ok(())
+}
+
+fn volume_label(label: &str) -> Result<[u8; 11]> {
+    if label.len() > 11 || !label.bytes().all(|b| b.is_ascii_graphic() || b == b' ') {
+        return Err(format!("Invalid volume label '{}', expected up to 11 ASCII characters", label).into());
+    }
+
+    let mut bytes = [b' '; 11];
+    bytes[..label.len()].copy_from_slice(label.to_ascii_uppercase().as_bytes());
+
+    Ok(bytes)
+}
+
+/// Writes a master boot record with a single FAT32 (LBA) partition.
+fn write_mbr(image: &mut File, start: u32, sectors: u32) -> io::Result<()> {
+    let mut mbr = [0; SECTOR_SIZE as usize];
+    let entry = &mut mbr[0x1BE..0x1CE];
+
+    // CHS addresses are unused, mark them as out of range
+    entry[1..4].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+    entry[4] = 0x0C;
+    entry[5..8].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+    entry[8..12].copy_from_slice(&start.to_le_bytes());
+    entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+    mbr[0x1FE..].copy_from_slice(&[0x55, 0xAA]);
+
+    image.seek(SeekFrom::Start(0))?;
+    image.write_all(&mbr)
+}
+
+/// Restricts reads, writes and seeks to a byte range of the disk image.
+struct Partition<T> {
+    inner: T,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<T: Seek> Partition<T> {
+    fn new(inner: T, start: u64, len: u64) -> Self {
+        Self {
+            inner,
+            start,
+            len,
+            pos: 0,
+        }
+    }
+
+    /// Seeks the underlying disk and returns how many bytes are left in the partition
+    fn prepare(&mut self, len: usize) -> io::Result<usize> {
+        self.inner.seek(SeekFrom::Start(self.start + self.pos))?;
+
+        Ok(cmp::min(len as u64, self.len.saturating_sub(self.pos)) as usize)
+    }
+}
+
+impl<T: Read + Seek> Read for Partition<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.prepare(buf.len())?;
+        let read = self.inner.read(&mut buf[..len])?;
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<T: Write + Seek> Write for Partition<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.prepare(buf.len())?;
+        let written = self.inner.write(&buf[..len])?;
+        self.pos += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T> Seek for Partition<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+
+        self.pos = pos
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seek before the start of the partition"))?;
+
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+use firmtool::symbols::{self, SymbolTable};
+use crate::Result;
+use crate::util::{self, parse_u32};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// ELF file or symbol table written by `build --symbol-maps`
+    symbols: PathBuf,
+    /// Addresses to look up, e.g. copied from a panic screen
+    #[arg(required = true, value_parser = parse_u32)]
+    addrs: Vec<u32>,
+}
+
+pub fn run(args: Args) -> Result {
+    let file = util::read(&args.symbols)?;
+    let table = if file.starts_with(&symbols::MAGIC) {
+        SymbolTable::from_bytes(&file)?
+    } else {
+        SymbolTable::from_elf(&file)?
+    };
+
+    for addr in args.addrs {
+        match table.lookup(addr) {
+            Some((symbol, 0)) => println!("0x{:08X} {}", addr, symbol.name),
+            Some((symbol, offset)) => println!("0x{:08X} {}+0x{:X}", addr, symbol.name, offset),
+            None => println!("0x{:08X} ??", addr),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+use sha2::{Sha256, Digest};
+use firmtool::{Firm, Cpu};
+use firmtool::header;
+use firmtool::memmap::{self, Model};
+use crate::Result;
+use crate::util::{self, hex};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// FIRM image to check
+    firm: PathBuf,
+    /// Check load addresses against the New 3DS memory map
+    #[arg(long)]
+    n3ds: bool,
+}
+
+pub fn run(args: Args) -> Result {
+    let model = if args.n3ds { Model::N3ds } else { Model::O3ds };
+    let firm = util::read(&args.firm)?;
+    let firm = Firm::parse(&firm)?;
+    let header = firm.header();
+    let mut report = Report::default();
+
+    for (i, section) in firm.section_headers() {
+        let end = u64::from(section.offset) + u64::from(section.size);
+        let in_bounds = section.offset >= header::SIZE && end <= firm.data().len() as u64;
+
+        report.check(in_bounds, format!(
+            "section {} lies within the file (0x{:08X}..0x{:08X}, file size 0x{:08X})",
+            i, section.offset, end, firm.data().len(),
+        ));
+
+        if let Some(data) = firm.section_data(i) {
+            let sha256: [u8; 32] = Sha256::digest(data).into();
+
+            report.check(sha256 == section.sha256, format!(
+                "section {} sha256 matches ({})", i, hex(&sha256),
+            ));
+        }
+
+        let addr_end = u64::from(section.addr) + u64::from(section.size);
+
+        report.check(addr_end <= 1 << 32, format!(
+            "section {} lies within the address space (0x{:08X}..0x{:08X})",
+            i, section.addr, addr_end,
+        ));
+
+        let issues = memmap::check_section(model, section.addr, section.size, section.copy_method);
+
+        report.check(!issues.iter().any(|issue| issue.is_error()), format!(
+            "section {} can be loaded to 0x{:08X} with {:?}", i, section.addr, section.copy_method,
+        ));
+
+        for issue in issues {
+            report.note(&format!("section {}: {}", i, issue));
+        }
+    }
+
+    for (i, a) in firm.section_headers() {
+        for (j, b) in firm.section_headers().filter(|&(j, _)| j > i) {
+            report.check(!a.overlaps(b), format!(
+                "sections {} (0x{:08X}+0x{:X}) and {} (0x{:08X}+0x{:X}) do not overlap",
+                i, a.addr, a.size, j, b.addr, b.size,
+            ));
+        }
+    }
+
+    let entrypoints = [
+        (Cpu::Arm9, header.arm9_entrypoint),
+        (Cpu::Arm11, header.arm11_entrypoint),
+    ];
+
+    for &(cpu, entrypoint) in &entrypoints {
+        // Images without an ARM11 payload leave its entrypoint at 0
+        if cpu == Cpu::Arm11 && entrypoint == 0 {
+            report.note("no arm11 entrypoint");
+            continue;
+        }
+
+        let section = firm.section_headers().find(|(_, section)| section.contains_addr(entrypoint));
+
+        report.check(section.is_some(), match section {
+            Some((i, _)) => format!("{} entrypoint 0x{:08X} lies in section {}", cpu, entrypoint, i),
+            None => format!("{} entrypoint 0x{:08X} lies in a section", cpu, entrypoint),
+        });
+
+        if let Some(issue) = memmap::check_entrypoint(cpu, entrypoint) {
+            report.check(false, issue.to_string());
+        }
+    }
+
+    let signature = header.signature();
+
+    report.check(signature.is_known(), format!(
+        "signature is known ({})", signature,
+    ));
+
+    if report.failed > 0 {
+        Err(format!("{} check(s) failed", report.failed))?
+    }
+
+    println!("all checks passed");
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub failed: usize,
+}
+
+impl Report {
+    pub fn check(&mut self, ok: bool, description: String) {
+        if ok {
+            println!("[ ok ] {}", description);
+        } else {
+            println!("[FAIL] {}", description);
+            self.failed += 1;
+        }
+    }
+
+    pub fn note(&self, description: &str) {
+        println!("       {}", description);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Result, FirmError};
+use crate::memmap;
+use std::io::Write;
+use std::iter;
+use std::convert::{TryFrom, TryInto};
+use byteorder::{WriteBytesExt, LE};
+
+const EHDR_SIZE: u16 = 0x34;
+const PHDR_SIZE: u16 = 0x20;
+const SHDR_SIZE: u16 = 0x28;
+const EM_ARM: u16 = 40;
+const EF_ARM_EABI_VER5: u32 = 0x0500_0000;
+const SHSTRTAB: &[u8] = b"\0.text\0.shstrtab\0";
+
+pub struct Program {
+    pub entrypoint: u32,
+    pub segments: Vec<Segment>,
+}
+
+/// A run of contiguous `PT_LOAD` segments.
+pub struct Segment {
+    pub addr: u32,
+    pub data: Vec<u8>,
+}
+
+/// Extracts the loadable segments of an ELF file.
+/// Each run of contiguous segments becomes its own `Segment`.
+pub fn extract_program(file: &[u8]) -> Result<Program> {
+    use goblin::elf::{Elf, program_header::PT_LOAD};
+
+    let elf = Elf::parse(file)?;
+    let entrypoint = elf.entry.try_into().map_err(|_| FirmError::AddressOutOfRange(elf.entry))?;
+    let mut segments = Vec::new();
+    let mut run = None;
+    let mut previous_end = None;
+    // Nothing bigger than the largest memory region can be loaded,
+    // which also keeps bogus sizes from exhausting memory
+    let max_size = memmap::regions().iter()
+        .map(|region| region.range.end - region.range.start)
+        .max()
+        .unwrap_or(0);
+
+    for ph in &elf.program_headers {
+        if ph.p_type != PT_LOAD || ph.p_memsz == 0 {
+            continue;
+        }
+
+        if ph.p_paddr != ph.p_vaddr {
+            return Err(FirmError::VirtualAddress { vaddr: ph.p_vaddr, paddr: ph.p_paddr });
+        }
+
+        if ph.p_filesz > ph.p_memsz {
+            return Err(FirmError::SegmentSize { file_size: ph.p_filesz, mem_size: ph.p_memsz });
+        }
+
+        let end = ph.p_paddr.checked_add(ph.p_memsz)
+            .filter(|&end| end <= 1 << 32 && ph.p_memsz <= max_size)
+            .ok_or(FirmError::SegmentTooLarge { addr: ph.p_paddr, size: ph.p_memsz })?;
+
+        let segment = ph.p_offset.checked_add(ph.p_filesz)
+            .and_then(|file_end| file.get(usize::try_from(ph.p_offset).ok()?..usize::try_from(file_end).ok()?))
+            .ok_or(FirmError::SegmentOutsideFile { offset: ph.p_offset, size: ph.p_filesz })?;
+
+        // Segments that are not contiguous with the previous one start a new section
+        if previous_end != Some(ph.p_paddr) {
+            segments.extend(run.take());
+        }
+
+        previous_end = Some(end);
+
+        let addr = ph.p_paddr.try_into().map_err(|_| FirmError::AddressOutOfRange(ph.p_paddr))?;
+        let data = &mut run.get_or_insert(Segment { addr, data: Vec::new() }).data;
+
+        if data.len() as u64 + ph.p_memsz > max_size {
+            return Err(FirmError::SegmentTooLarge { addr: ph.p_paddr, size: data.len() as u64 + ph.p_memsz });
+        }
+
+        data.extend_from_slice(segment);
+
+        let zero_fill = ph.p_memsz - ph.p_filesz;
+        data.extend(iter::repeat_n(0, zero_fill as usize));
+    }
+
+    segments.extend(run);
+
+    if segments.is_empty() {
+        return Err(FirmError::NoLoadableSegments);
+    }
+
+    Ok(Program {
+        entrypoint,
+        segments,
+    })
+}
+
+/// Wraps a flat binary into a minimal 32-bit ARM ELF executable
+/// with a single loadable `.text` section at `addr`.
+pub fn write_executable<W: Write>(w: &mut W, addr: u32, entrypoint: u32, data: &[u8]) -> Result {
+    let data_offset = u32::from(EHDR_SIZE) + u32::from(PHDR_SIZE);
+    let size = u32::try_from(data.len()).ok()
+        .filter(|&size| size < u32::MAX - 0x100)
+        .ok_or(FirmError::SectionOverflow { addr, size: data.len() as u64 })?;
+    let shstrtab_offset = data_offset + size;
+    let shdrs_offset = align4(shstrtab_offset + SHSTRTAB.len() as u32);
+
+    // ELF header
+    w.write_all(b"\x7FELF")?;
+    w.write_u8(1)?; // ELFCLASS32
+    w.write_u8(1)?; // ELFDATA2LSB
+    w.write_u8(1)?; // EV_CURRENT
+    w.write_all(&[0; 9])?;
+    w.write_u16::<LE>(2)?; // ET_EXEC
+    w.write_u16::<LE>(EM_ARM)?;
+    w.write_u32::<LE>(1)?; // EV_CURRENT
+    w.write_u32::<LE>(entrypoint)?;
+    w.write_u32::<LE>(u32::from(EHDR_SIZE))?;
+    w.write_u32::<LE>(shdrs_offset)?;
+    w.write_u32::<LE>(EF_ARM_EABI_VER5)?;
+    w.write_u16::<LE>(EHDR_SIZE)?;
+    w.write_u16::<LE>(PHDR_SIZE)?;
+    w.write_u16::<LE>(1)?;
+    w.write_u16::<LE>(SHDR_SIZE)?;
+    w.write_u16::<LE>(3)?;
+    w.write_u16::<LE>(2)?;
+
+    // Program header
+    w.write_u32::<LE>(1)?; // PT_LOAD
+    w.write_u32::<LE>(data_offset)?;
+    w.write_u32::<LE>(addr)?;
+    w.write_u32::<LE>(addr)?;
+    w.write_u32::<LE>(size)?;
+    w.write_u32::<LE>(size)?;
+    w.write_u32::<LE>(7)?; // PF_R | PF_W | PF_X
+    w.write_u32::<LE>(4)?;
+
+    w.write_all(data)?;
+    w.write_all(SHSTRTAB)?;
+    w.write_all(&vec![0; (shdrs_offset - shstrtab_offset) as usize - SHSTRTAB.len()])?;
+
+    // Section headers: null, .text, .shstrtab
+    w.write_all(&[0; SHDR_SIZE as usize])?;
+    write_section_header(w, 1, 1, 0x7, addr, data_offset, size)?; // SHT_PROGBITS, WAX
+    write_section_header(w, 7, 3, 0, 0, shstrtab_offset, SHSTRTAB.len() as u32)?; // SHT_STRTAB
+
+    Ok(())
+}
+
+fn write_section_header<W: Write>(w: &mut W, name: u32, kind: u32, flags: u32, addr: u32, offset: u32, size: u32) -> Result {
+    w.write_u32::<LE>(name)?;
+    w.write_u32::<LE>(kind)?;
+    w.write_u32::<LE>(flags)?;
+    w.write_u32::<LE>(addr)?;
+    w.write_u32::<LE>(offset)?;
+    w.write_u32::<LE>(size)?;
+    w.write_u32::<LE>(0)?; // link
+    w.write_u32::<LE>(0)?; // info
+    w.write_u32::<LE>(4)?; // addralign
+    w.write_u32::<LE>(0)?; // entsize
+
+    Ok(())
+}
+
+fn align4(n: u32) -> u32 {
+    (n + 3) & !3
+}
@@ -0,0 +1,137 @@
+use std::{fmt, io};
+use std::ops::Range;
+use std::error::Error;
+use crate::Cpu;
+use crate::memmap::Issue;
+
+#[derive(Debug)]
+pub enum FirmError {
+    Io(io::Error),
+    Elf(goblin::error::Error),
+    /// A FIRM image holds at most four sections
+    TooManySections(usize),
+    MissingEntrypoint(Cpu),
+    EntrypointOutsideSections {
+        cpu: Cpu,
+        entrypoint: u32,
+    },
+    /// The section does not fit into the 32-bit address space
+    SectionOverflow {
+        addr: u32,
+        size: u64,
+    },
+    /// Two sections share part of the address space
+    SectionOverlap {
+        first: usize,
+        first_range: Range<u32>,
+        second: usize,
+        second_range: Range<u32>,
+    },
+    InvalidMagic([u8; 4]),
+    InvalidCopyMethod(u32),
+    /// Signatures are exactly 0x100 bytes long
+    InvalidSignatureSize(usize),
+    /// The ELF uses a virtual address that differs from its physical one
+    VirtualAddress {
+        vaddr: u64,
+        paddr: u64,
+    },
+    /// An ELF segment has a larger file size than memory size
+    SegmentSize {
+        file_size: u64,
+        mem_size: u64,
+    },
+    /// The file contents of an ELF segment extend past the end of the file
+    SegmentOutsideFile {
+        offset: u64,
+        size: u64,
+    },
+    /// An ELF segment does not fit into any memory region
+    SegmentTooLarge {
+        addr: u64,
+        size: u64,
+    },
+    NoLoadableSegments,
+    /// An ELF address does not fit into 32 bits
+    AddressOutOfRange(u64),
+    InvalidSymbolTable,
+    NoSuchSection(usize),
+    /// The section data is not contained in the image
+    SectionOutsideImage(usize),
+    /// Malformed line in a content hash file
+    InvalidContentHashes(usize),
+    /// The content hash file lacks the digest of the whole image
+    MissingImageDigest,
+    /// A section or entrypoint violates the memory map
+    MemoryMap {
+        section: Option<usize>,
+        issue: Issue,
+    },
+}
+
+impl fmt::Display for FirmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirmError::Io(err) => write!(f, "{}", err),
+            FirmError::Elf(err) => write!(f, "Invalid ELF file: {}", err),
+            FirmError::TooManySections(count) => write!(f, "Firm cannot contain more than four sections, got {}", count),
+            FirmError::MissingEntrypoint(cpu) => write!(f, "Missing {} entrypoint", cpu),
+            FirmError::EntrypointOutsideSections { cpu, entrypoint } => {
+                write!(f, "{} entrypoint 0x{:08X} does not point into any section", cpu, entrypoint)
+            },
+            FirmError::SectionOverflow { addr, size } => {
+                write!(f, "Section at 0x{:08X} with size 0x{:X} lies outside of address space", addr, size)
+            },
+            FirmError::SectionOverlap { first, first_range, second, second_range } => write!(f,
+                "Section {} (0x{:08X}..0x{:08X}) overlaps section {} (0x{:08X}..0x{:08X})",
+                first, first_range.start, first_range.end, second, second_range.start, second_range.end,
+            ),
+            FirmError::InvalidMagic(magic) => write!(f, "Invalid FIRM magic {:02X?}", magic),
+            FirmError::InvalidCopyMethod(copy_method) => write!(f, "Invalid copy method {}", copy_method),
+            FirmError::InvalidSignatureSize(size) => write!(f, "Signature must be 0x100 bytes, got 0x{:X}", size),
+            FirmError::VirtualAddress { vaddr, paddr } => {
+                write!(f, "Virtual addresses are not supported (vaddr 0x{:X}, paddr 0x{:X})", vaddr, paddr)
+            },
+            FirmError::SegmentSize { file_size, mem_size } => {
+                write!(f, "Segment file size 0x{:X} exceeds mem size 0x{:X}", file_size, mem_size)
+            },
+            FirmError::SegmentOutsideFile { offset, size } => {
+                write!(f, "Segment at file offset 0x{:X} with size 0x{:X} extends past the end of the file", offset, size)
+            },
+            FirmError::SegmentTooLarge { addr, size } => {
+                write!(f, "Segment at 0x{:X} with size 0x{:X} does not fit into memory", addr, size)
+            },
+            FirmError::NoLoadableSegments => write!(f, "No suitable segments found"),
+            FirmError::AddressOutOfRange(addr) => write!(f, "Address 0x{:X} does not fit into 32 bits", addr),
+            FirmError::InvalidSymbolTable => write!(f, "Invalid or truncated symbol table"),
+            FirmError::NoSuchSection(index) => write!(f, "There is no section {}", index),
+            FirmError::SectionOutsideImage(index) => write!(f, "Section {} lies outside of the image", index),
+            FirmError::InvalidContentHashes(line) => write!(f, "Invalid content hashes on line {}", line),
+            FirmError::MissingImageDigest => write!(f, "Content hashes lack the digest of the image"),
+            FirmError::MemoryMap { section: Some(index), issue } => write!(f, "Section {}: {}", index, issue),
+            FirmError::MemoryMap { section: None, issue } => write!(f, "{}", issue),
+        }
+    }
+}
+
+impl Error for FirmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FirmError::Io(err) => Some(err),
+            FirmError::Elf(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FirmError {
+    fn from(err: io::Error) -> Self {
+        FirmError::Io(err)
+    }
+}
+
+impl From<goblin::error::Error> for FirmError {
+    fn from(err: goblin::error::Error) -> Self {
+        FirmError::Elf(err)
+    }
+}
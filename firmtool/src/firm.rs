@@ -0,0 +1,46 @@
+use crate::Result;
+use crate::header::{self, Header, SectionHeader};
+
+/// A FIRM image read back from disk.
+pub struct Firm<'a> {
+    header: Header,
+    data: &'a [u8],
+}
+
+impl<'a> Firm<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let header = Header::from_reader(&mut &data[..])?;
+
+        Ok(Self { header, data })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the raw image, including the header.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Iterates over all used section slots together with their index.
+    pub fn section_headers(&self) -> impl Iterator<Item = (usize, &SectionHeader)> {
+        self.header.section_headers.iter()
+            .enumerate()
+            .filter(|(_, section)| !section.is_empty())
+    }
+
+    /// Returns the contents of a section or `None`
+    /// if the section does not lie within the image.
+    pub fn section_data(&self, index: usize) -> Option<&'a [u8]> {
+        let section = self.header.section_headers.get(index)?;
+        let start = section.offset as usize;
+        let end = start.checked_add(section.size as usize)?;
+
+        if start < header::SIZE as usize {
+            return None;
+        }
+
+        self.data.get(start..end)
+    }
+}
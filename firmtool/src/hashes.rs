@@ -0,0 +1,133 @@
+//! SHA-256 digests of a FIRM image and its sections,
+//! stored next to reproducible builds for later auditing.
+//!
+//! The sidecar file holds one digest per line,
+//! the whole image first and then every section:
+//!
+//! ```text
+//! <sha256>  firm
+//! <sha256>  section0 0x08000000 0x00001000
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use sha2::{Sha256, Digest};
+use crate::{Firm, FirmError, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentHashes {
+    pub firm: [u8; 32],
+    pub sections: Vec<SectionHash>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionHash {
+    pub index: usize,
+    pub addr: u32,
+    pub size: u32,
+    pub sha256: [u8; 32],
+}
+
+impl ContentHashes {
+    /// Hashes the contents of an image. Section digests are computed
+    /// from the section data, not taken from the header.
+    pub fn new(firm: &Firm) -> Result<Self> {
+        let mut sections = Vec::new();
+
+        for (index, section) in firm.section_headers() {
+            let data = firm.section_data(index).ok_or(FirmError::SectionOutsideImage(index))?;
+
+            sections.push(SectionHash {
+                index,
+                addr: section.addr,
+                size: section.size,
+                sha256: Sha256::digest(data).into(),
+            });
+        }
+
+        Ok(Self {
+            firm: Sha256::digest(firm.data()).into(),
+            sections,
+        })
+    }
+}
+
+impl fmt::Display for ContentHashes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}  firm", Hex(&self.firm))?;
+
+        for section in &self.sections {
+            writeln!(f, "{}  section{} 0x{:08X} 0x{:08X}",
+                Hex(&section.sha256), section.index, section.addr, section.size,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for ContentHashes {
+    type Err = FirmError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut firm = None;
+        let mut sections = Vec::new();
+
+        for (i, line) in s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let invalid = || FirmError::InvalidContentHashes(i + 1);
+            let mut fields = line.split_whitespace();
+            let sha256 = fields.next().and_then(parse_sha256).ok_or_else(invalid)?;
+            let name = fields.next().ok_or_else(invalid)?;
+
+            if name == "firm" {
+                firm = Some(sha256);
+            } else {
+                let index = name.strip_prefix("section")
+                    .and_then(|index| index.parse().ok())
+                    .ok_or_else(invalid)?;
+                let mut number = || fields.next()
+                    .and_then(|n| n.strip_prefix("0x"))
+                    .and_then(|n| u32::from_str_radix(n, 16).ok())
+                    .ok_or_else(invalid);
+
+                sections.push(SectionHash {
+                    index,
+                    addr: number()?,
+                    size: number()?,
+                    sha256,
+                });
+            }
+
+            if fields.next().is_some() {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Self {
+            firm: firm.ok_or(FirmError::MissingImageDigest)?,
+            sections,
+        })
+    }
+}
+
+fn parse_sha256(s: &str) -> Option<[u8; 32]> {
+    let mut sha256 = [0; 32];
+
+    if s.len() != 64 || !s.is_ascii() {
+        return None;
+    }
+
+    for (byte, digits) in sha256.iter_mut().zip(s.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+
+    Some(sha256)
+}
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
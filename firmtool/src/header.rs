@@ -1,8 +1,13 @@
-use crate::Result;
-use std::io::Write;
-use byteorder::{WriteBytesExt, LE};
+use crate::{Result, FirmError, Signature};
+use std::io::{Read, Write};
+use std::str::FromStr;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use num_traits::FromPrimitive;
+
+pub use common::firm::{Metadata, TargetModel, Version};
 
 pub const SIZE: u32 = 0x200;
+pub const MAGIC: [u8; 4] = *b"FIRM";
 
 pub struct Header {
     pub boot_priority: u32,
@@ -31,8 +36,51 @@ impl Header {
         Self::default()
     }
 
+    pub fn signature(&self) -> Signature {
+        Signature::identify(&self.rsa_signature)
+    }
+
+    /// Returns the metadata block stored in the reserved bytes, if any.
+    pub fn metadata(&self) -> Option<Metadata> {
+        Metadata::from_bytes(&self.reserved)
+    }
+
+    pub fn from_reader<R: Read>(r: &mut R) -> Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+
+        if magic != MAGIC {
+            return Err(FirmError::InvalidMagic(magic));
+        }
+
+        let boot_priority = r.read_u32::<LE>()?;
+        let arm11_entrypoint = r.read_u32::<LE>()?;
+        let arm9_entrypoint = r.read_u32::<LE>()?;
+
+        let mut reserved = [0; 0x30];
+        r.read_exact(&mut reserved)?;
+
+        let mut section_headers = <[SectionHeader; 4]>::default();
+
+        for section_header in &mut section_headers {
+            *section_header = SectionHeader::from_reader(r)?;
+        }
+
+        let mut rsa_signature = [0; 0x100];
+        r.read_exact(&mut rsa_signature)?;
+
+        Ok(Self {
+            boot_priority,
+            arm11_entrypoint,
+            arm9_entrypoint,
+            reserved,
+            section_headers,
+            rsa_signature,
+        })
+    }
+
     pub fn to_writer<W: Write>(&self, w: &mut W) -> Result {
-        w.write_all(b"FIRM")?;
+        w.write_all(&MAGIC)?;
         w.write_u32::<LE>(self.boot_priority)?;
         w.write_u32::<LE>(self.arm11_entrypoint)?;
         w.write_u32::<LE>(self.arm9_entrypoint)?;
@@ -58,6 +106,20 @@ pub struct SectionHeader {
 }
 
 impl SectionHeader {
+    fn from_reader<R: Read>(r: &mut R) -> Result<Self> {
+        let offset = r.read_u32::<LE>()?;
+        let addr = r.read_u32::<LE>()?;
+        let size = r.read_u32::<LE>()?;
+        let copy_method = r.read_u32::<LE>()?;
+        let copy_method = CopyMethod::from_u32(copy_method)
+            .ok_or(FirmError::InvalidCopyMethod(copy_method))?;
+
+        let mut sha256 = [0; 32];
+        r.read_exact(&mut sha256)?;
+
+        Ok(Self { offset, addr, size, copy_method, sha256 })
+    }
+
     fn to_writer<W: Write>(&self, w: &mut W) -> Result {
         w.write_u32::<LE>(self.offset)?;
         w.write_u32::<LE>(self.addr)?;
@@ -67,17 +129,47 @@ impl SectionHeader {
 
         Ok(())
     }
+
+    /// Unused section slots are all zeros.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn contains_addr(&self, addr: u32) -> bool {
+        let addr = u64::from(addr);
+        let start = u64::from(self.addr);
+        let end = start + u64::from(self.size);
+
+        (start .. end).contains(&addr)
+    }
+
+    pub fn overlaps(&self, other: &SectionHeader) -> bool {
+        let start = u64::from(self.addr);
+        let end = start + u64::from(self.size);
+        let other_start = u64::from(other.addr);
+        let other_end = other_start + u64::from(other.size);
+
+        start < other_end && other_start < end
+    }
 }
 
-#[derive(FromPrimitive,Copy,Clone)]
+#[derive(FromPrimitive,Copy,Clone,Debug,PartialEq,Eq,Default)]
 pub enum CopyMethod {
+    #[default]
     NDMA = 0,
     XDMA = 1,
     CPU = 2,
 }
 
-impl Default for CopyMethod {
-    fn default() -> Self {
-        CopyMethod::NDMA
+impl FromStr for CopyMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "NDMA" => Ok(CopyMethod::NDMA),
+            "XDMA" => Ok(CopyMethod::XDMA),
+            "CPU" => Ok(CopyMethod::CPU),
+            _ => Err(format!("Invalid copy method '{}', expected NDMA, XDMA or CPU", s)),
+        }
     }
 }
@@ -1,13 +1,41 @@
 #[macro_use] extern crate num_derive;
 
-pub type Result<T = (), E = Box<std::error::Error>> = std::result::Result<T, E>;
+use std::fmt;
+
+pub type Result<T = (), E = FirmError> = std::result::Result<T, E>;
+
+mod error;
+pub use error::FirmError;
 
 pub mod header;
 pub use header::CopyMethod;
 
+pub mod elf;
+pub mod memmap;
+pub mod symbols;
+
+mod firm;
+pub use firm::Firm;
+
 mod builder;
-pub use builder::{Builder, Section};
+pub use builder::{Builder, Section, SectionLayout, Alignment};
+
+pub mod hashes;
+
+pub mod signature;
+pub use signature::Signature;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cpu {
+    Arm9,
+    Arm11,
+}
 
-pub mod signature {
-    pub const NAND_RETAIL: [u8; 0x100] = *include_bytes!("../signatures/nand_retail");
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Cpu::Arm9 => "arm9",
+            Cpu::Arm11 => "arm11",
+        })
+    }
 }
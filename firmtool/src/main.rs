@@ -1,85 +1,60 @@
-use std::fs;
-use std::env::args;
-use std::iter;
-use std::convert::{TryFrom, TryInto};
-use firmtool::{Result, Builder, Section, CopyMethod};
-
-fn main() {
-    let firm_path = args().nth(1).expect("arg1: must be firm output path");
-    let mut builder = Builder::new();
-
-    {
-        let path = args().nth(2).expect("arg2: must be arm9 elf path");
-        let arm9 = fs::read(path).unwrap();
-        let program = extract_elf_program(&arm9).unwrap();
-        let section = Section::new(program.addr, CopyMethod::NDMA, program.data).unwrap();
-        builder.arm9_entrypoint(program.entrypoint);
-        builder.add_section(section);
-    }
-
-    if let Some(path) = args().nth(3) {
-        let arm11 = fs::read(path).unwrap();
-        let program = extract_elf_program(&arm11).unwrap();
-        let section = Section::new(program.addr, CopyMethod::XDMA, program.data).unwrap();
-        builder.arm11_entrypoint(program.entrypoint);
-        builder.add_section(section);
-    }
-
-    let firm = builder.build().unwrap();
-    fs::write(firm_path, firm).unwrap();
+use std::process;
+use std::path::PathBuf;
+use clap::Parser;
+
+mod cmd;
+mod manifest;
+mod util;
+
+pub type Result<T = (), E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+
+/// Builds and inspects 3DS FIRM images
+#[derive(Parser)]
+#[command(version)]
+enum Cli {
+    /// Build a FIRM image from ELF files and raw binaries
+    Build(cmd::build::Args),
+    /// Print the header of a FIRM image
+    Parse {
+        firm: PathBuf,
+    },
+    /// Check the hashes, bounds and entrypoints of a FIRM image
+    Verify(cmd::verify::Args),
+    /// Dump the sections of a FIRM image to raw binaries
+    Extract(cmd::extract::Args),
+    /// Describe a FIRM image, its memory map and the largest symbols
+    Info(cmd::info::Args),
+    /// Turn addresses into function names and offsets
+    Symbolize(cmd::symbolize::Args),
+    /// Compare two FIRM images
+    Diff(cmd::diff::Args),
+    /// Create a FAT32 SD card image containing a FIRM image as boot.firm
+    Sdimage(cmd::sdimage::Args),
+    /// Compare a FIRM image against the content hashes of a reproducible build
+    CheckManifest(cmd::check_manifest::Args),
+    /// Convert a PNG into raw framebuffer data for the top or bottom screen
+    Image(cmd::image::Args),
+    /// Replace one section of an existing FIRM image
+    Patch(cmd::patch::Args),
 }
 
-fn extract_elf_program(file: &[u8]) -> Result<ElfProgram> {
-    use goblin::elf::{Elf, program_header::PT_LOAD};
-    
-    let elf = Elf::parse(file)?;
-    let entrypoint = u32::try_from(elf.entry)?;
-    let mut data = Vec::new();
-    let mut program_start = None;
-    let mut previous_end = None;
-
-    for ph in &elf.program_headers {
-        if ph.p_type != PT_LOAD || ph.p_memsz == 0 {
-            continue;
-        }
-
-        if ph.p_paddr != ph.p_vaddr {
-            Err("virtual addresses are not supported")?
-        }
-
-        if ph.p_filesz > ph.p_memsz {
-            Err("file size cannot exceed mem size")?
-        }
-
-        program_start.get_or_insert(ph.p_paddr);
-
-        if *previous_end.get_or_insert(ph.p_paddr) != ph.p_paddr {
-            println!("skipping {:?}", ph);
-            continue
-        }
-
-        previous_end = Some(ph.p_paddr + ph.p_memsz);
-
-        // TODO: check indices
-        let segment = &file[ph.file_range()];
-        data.extend_from_slice(segment);
-
-        // println!("segment = {:02x?}", segment);
-
-        let zero_fill = ph.p_memsz - ph.p_filesz;
-        data.extend(iter::repeat(0).take(zero_fill as usize));
+fn main() {
+    let result = match Cli::parse() {
+        Cli::Build(args) => cmd::build::run(args),
+        Cli::Parse { firm } => cmd::parse::run(&firm),
+        Cli::Verify(args) => cmd::verify::run(args),
+        Cli::Extract(args) => cmd::extract::run(args),
+        Cli::Info(args) => cmd::info::run(args),
+        Cli::Symbolize(args) => cmd::symbolize::run(args),
+        Cli::Diff(args) => cmd::diff::run(args),
+        Cli::Sdimage(args) => cmd::sdimage::run(args),
+        Cli::CheckManifest(args) => cmd::check_manifest::run(args),
+        Cli::Image(args) => cmd::image::run(args),
+        Cli::Patch(args) => cmd::patch::run(args),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
     }
-
-    Ok(ElfProgram {
-        entrypoint,
-        addr: program_start.ok_or("No suitable segments found")?
-            .try_into().map_err(|_| "Program is too big")?,
-        data,
-    })
-}
-
-struct ElfProgram {
-    entrypoint: u32,
-    addr: u32,
-    data: Vec<u8>,
 }
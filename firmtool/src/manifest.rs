@@ -0,0 +1,205 @@
+//! Declarative description of a FIRM image, e.g.
+//!
+//! ```toml
+//! output = "sd3.firm"
+//! priority = 0
+//! signature = "nand-retail"
+//!
+//! [[section]]
+//! elf = "arm9/target/sd3_arm9/debug/sd3_arm9"
+//! entry = "arm9"
+//!
+//! [[section]]
+//! raw = "splash.bin"
+//! addr = 0x18000000
+//! copy_method = "XDMA"
+//! ```
+//!
+//! Relative paths are resolved against the directory of the manifest.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer};
+use firmtool::{CopyMethod, Cpu, Signature, Alignment};
+use firmtool::header::TargetModel;
+use firmtool::memmap::Model;
+use crate::Result;
+use crate::util;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub output: Option<PathBuf>,
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub signature: SignatureName,
+    /// Custom 0x100 byte signature, takes precedence over `signature`
+    pub signature_file: Option<PathBuf>,
+    /// Model to check the memory map against, also recorded in the metadata
+    pub model: Option<ModelName>,
+    #[serde(default)]
+    pub sort: bool,
+    #[serde(default = "default_align")]
+    pub align: u32,
+    pub fill: Option<u8>,
+    /// Pad with zeros unless `fill` is set and write the content hashes next to the image
+    #[serde(default, alias = "hashes")]
+    pub reproducible: bool,
+    pub arm9_entry: Option<u32>,
+    pub arm11_entry: Option<u32>,
+    pub symbol_maps: Option<PathBuf>,
+    pub embed_symbols: Option<u32>,
+    /// Metadata block for the reserved header bytes
+    pub metadata: Option<MetadataSpec>,
+    #[serde(default, rename = "section")]
+    pub sections: Vec<SectionSpec>,
+    /// Directory relative paths and git lookups are based on
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+/// Either an ELF file or a raw binary loaded to `addr`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SectionSpec {
+    pub elf: Option<PathBuf>,
+    pub raw: Option<PathBuf>,
+    pub addr: Option<u32>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub copy_method: Option<CopyMethod>,
+    /// Use the entrypoint of this ELF file for the given CPU
+    pub entry: Option<CpuName>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataSpec {
+    /// Build version as `major.minor.patch`
+    pub version: String,
+    /// Commit hash as hex, taken from git when missing
+    pub git_hash: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureName {
+    #[default]
+    NandRetail,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelName {
+    O3ds,
+    N3ds,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuName {
+    Arm9,
+    Arm11,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest = util::read(path)?;
+        let manifest = String::from_utf8(manifest)?;
+        let mut manifest = toml::from_str::<Manifest>(&manifest)
+            .map_err(|err| format!("Invalid manifest {}: {}", path.display(), err))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        manifest.resolve_paths(base_dir);
+        manifest.base_dir = base_dir.to_path_buf();
+
+        Ok(manifest)
+    }
+
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        let paths = self.output.iter_mut()
+            .chain(&mut self.signature_file)
+            .chain(&mut self.symbol_maps)
+            .chain(self.sections.iter_mut().flat_map(|section| section.elf.iter_mut().chain(&mut section.raw)));
+
+        for path in paths {
+            *path = base_dir.join(&*path);
+        }
+    }
+
+    pub fn signature(&self) -> Result<Signature> {
+        Ok(match &self.signature_file {
+            Some(path) => Signature::from_file(path)?,
+            None => match self.signature {
+                SignatureName::NandRetail => Signature::NandRetail,
+            },
+        })
+    }
+
+    /// The model to validate against, the Old 3DS unless specified.
+    pub fn model(&self) -> Model {
+        match self.model {
+            Some(ModelName::O3ds) | None => Model::O3ds,
+            Some(ModelName::N3ds) => Model::N3ds,
+        }
+    }
+
+    /// The model recorded in the metadata, any model unless specified.
+    pub fn target_model(&self) -> TargetModel {
+        match self.model {
+            Some(_) => self.model().into(),
+            None => TargetModel::Any,
+        }
+    }
+
+    /// Sections are padded with 0xFF unless specified,
+    /// reproducible builds pad with zeros instead.
+    pub fn fill(&self) -> u8 {
+        match (self.fill, self.reproducible) {
+            (Some(fill), _) => fill,
+            (None, true) => 0,
+            (None, false) => 0xFF,
+        }
+    }
+
+    pub fn alignment(&self) -> Result<Alignment> {
+        match self.align {
+            512 => Ok(Alignment::Sector),
+            16 => Ok(Alignment::Block),
+            align => Err(format!("Invalid alignment {}, expected 512 or 16", align).into()),
+        }
+    }
+}
+
+impl CpuName {
+    pub fn cpu(self) -> Cpu {
+        match self {
+            CpuName::Arm9 => Cpu::Arm9,
+            CpuName::Arm11 => Cpu::Arm11,
+        }
+    }
+}
+
+impl From<Cpu> for CpuName {
+    fn from(cpu: Cpu) -> Self {
+        match cpu {
+            Cpu::Arm9 => CpuName::Arm9,
+            Cpu::Arm11 => CpuName::Arm11,
+        }
+    }
+}
+
+fn default_align() -> u32 {
+    512
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+
+    s.map(|s| s.parse().map_err(serde::de::Error::custom)).transpose()
+}
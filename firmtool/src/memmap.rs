@@ -0,0 +1,167 @@
+//! Validation of FIRM load addresses against the 3DS memory map in `common::mem`.
+
+use std::fmt;
+use std::ops::Range;
+use common::mem::{arm9, arm11};
+use crate::{CopyMethod, Cpu};
+use crate::header::TargetModel;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Model {
+    /// Old 3DS / 2DS
+    #[default]
+    O3ds,
+    /// New 3DS / New 2DS
+    N3ds,
+}
+
+impl From<Model> for TargetModel {
+    fn from(model: Model) -> Self {
+        match model {
+            Model::O3ds => TargetModel::O3ds,
+            Model::N3ds => TargetModel::N3ds,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(message: String) -> Self {
+        Self { severity: Severity::Error, message }
+    }
+
+    fn warning(message: String) -> Self {
+        Self { severity: Severity::Warning, message }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A memory region that sections may be loaded into.
+pub struct Region {
+    pub name: &'static str,
+    pub range: Range<u64>,
+    /// Whether the ARM11 side XDMA can write to the region
+    pub xdma: bool,
+    pub n3ds_only: bool,
+}
+
+/// Memory the boot ROM can load sections into.
+pub fn regions() -> Vec<Region> {
+    vec![
+        region("ARM9 memory", arm9::PRIVATE_MEM, false, false),
+        region("ARM9 memory extension", arm9::n3ds::EXTENSION, false, true),
+        region("VRAM", arm9::VRAM, true, false),
+        region("DSP memory", arm9::DSP_MEM, true, false),
+        region("AXI WRAM", arm9::AXI_WRAM, true, false),
+        region("FCRAM", arm9::FCRAM, true, false),
+        region("N3DS extra FCRAM", arm9::n3ds::EXTRA_FCRAM, true, true),
+        region("N3DS extra memory", arm11::n3ds::EXTRA_MEM, true, true),
+    ]
+}
+
+/// Returns the region containing `addr`, independent of the model.
+pub fn region_of(addr: u32) -> Option<Region> {
+    regions().into_iter().find(|region| region.range.contains(&u64::from(addr)))
+}
+
+/// Memory that must never be overwritten while booting.
+fn reserved() -> Vec<(&'static str, Range<u64>)> {
+    let bootrom = |range: std::ops::RangeInclusive<usize>| *range.start() as u64 .. *range.end() as u64 + 1;
+
+    vec![
+        ("the ARM9 ITCM", to_u64(arm9::INSTRUCTION_TCM1)),
+        ("the boot9 stack in DTCM", to_u64(arm9::DATA_TCM)),
+        ("the ARM9 bootrom", bootrom(arm9::BOOTROM)),
+        ("the ARM11 bootrom", to_u64(arm11::BOOTROM)),
+        ("the ARM11 private memory", to_u64(arm11::PRIVATE_MEM)),
+    ]
+}
+
+/// Checks whether a section can be loaded to `addr` with the given copy method.
+pub fn check_section(model: Model, addr: u32, size: u32, copy_method: CopyMethod) -> Vec<Issue> {
+    let range = u64::from(addr) .. u64::from(addr) + u64::from(size);
+    let mut issues = Vec::new();
+
+    for (name, reserved) in reserved() {
+        if overlaps(&range, &reserved) {
+            issues.push(Issue::error(format!("overlaps {}", name)));
+        }
+    }
+
+    let region = regions().into_iter()
+        .find(|region| region.range.start <= range.start && range.end <= region.range.end);
+
+    let region = match region {
+        Some(region) => region,
+        None => {
+            issues.push(Issue::error(format!(
+                "0x{:08X}..0x{:08X} does not lie within a single writable memory region",
+                range.start, range.end,
+            )));
+            return issues;
+        },
+    };
+
+    if region.n3ds_only && model != Model::N3ds {
+        issues.push(Issue::error(format!("{} only exists on the New 3DS", region.name)));
+    }
+
+    if copy_method == CopyMethod::XDMA && !region.xdma {
+        issues.push(Issue::error(format!("XDMA cannot reach {}", region.name)));
+    }
+
+    // The ARM11 exception vectors live at the very end of AXI WRAM
+    let vectors = arm11::AXI_WRAM.end as u64 - 0x60 .. arm11::AXI_WRAM.end as u64;
+
+    if overlaps(&range, &vectors) {
+        issues.push(Issue::warning("overlaps the ARM11 exception vectors at the end of AXI WRAM".into()));
+    }
+
+    issues
+}
+
+/// Checks whether the given CPU can execute code at `entrypoint`.
+pub fn check_entrypoint(cpu: Cpu, entrypoint: u32) -> Option<Issue> {
+    let in_arm9_mem = [arm9::PRIVATE_MEM, arm9::n3ds::EXTENSION].iter()
+        .any(|range| range.contains(&(entrypoint as usize)));
+
+    if cpu == Cpu::Arm11 && in_arm9_mem {
+        return Some(Issue::error(format!(
+            "arm11 entrypoint 0x{:08X} lies in ARM9 memory, which the ARM11 cannot access", entrypoint,
+        )));
+    }
+
+    None
+}
+
+fn region(name: &'static str, range: Range<usize>, xdma: bool, n3ds_only: bool) -> Region {
+    Region { name, range: to_u64(range), xdma, n3ds_only }
+}
+
+fn to_u64(range: Range<usize>) -> Range<u64> {
+    range.start as u64 .. range.end as u64
+}
+
+fn overlaps(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.start < b.end && b.start < a.end
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+use std::path::Path;
+use std::convert::TryFrom;
+use crate::{Result, FirmError};
+
+pub const NAND_RETAIL: [u8; 0x100] = *include_bytes!("../signatures/nand_retail");
+
+/// The RSA signature embedded in a FIRM header.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum Signature {
+    /// Retail sighax signature for booting from NAND
+    #[default]
+    NandRetail,
+    /// Any other signature, e.g. the SPI (ntrboot) or dev unit sighax blobs
+    Custom(Box<[u8; 0x100]>),
+}
+
+impl Signature {
+    pub const KNOWN: [Signature; 1] = [
+        Signature::NandRetail,
+    ];
+
+    /// Recognises one of the known signatures,
+    /// falling back to a custom signature otherwise.
+    pub fn identify(bytes: &[u8; 0x100]) -> Self {
+        Self::KNOWN.iter()
+            .find(|signature| signature.known_bytes() == Some(bytes))
+            .cloned()
+            .unwrap_or_else(|| Signature::Custom(Box::new(*bytes)))
+    }
+
+    /// Reads a custom signature from a 0x100 byte file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let bytes = <[u8; 0x100]>::try_from(&bytes[..])
+            .map_err(|_| FirmError::InvalidSignatureSize(bytes.len()))?;
+
+        Ok(Signature::Custom(Box::new(bytes)))
+    }
+
+    pub fn bytes(&self) -> &[u8; 0x100] {
+        match self {
+            Signature::NandRetail => &NAND_RETAIL,
+            Signature::Custom(bytes) => bytes,
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Signature::Custom(_))
+    }
+
+    fn known_bytes(&self) -> Option<&'static [u8; 0x100]> {
+        match self {
+            Signature::NandRetail => Some(&NAND_RETAIL),
+            Signature::Custom(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Signature::NandRetail => "NAND retail",
+            Signature::Custom(_) => "custom",
+        })
+    }
+}
@@ -0,0 +1,145 @@
+//! Address to symbol tables for turning raw addresses into function names.
+//!
+//! Tables can be stored in a compact little endian format:
+//!
+//! | offset | size       | description                                  |
+//! |--------|------------|----------------------------------------------|
+//! | 0x0    | 4          | magic `SYMS`                                 |
+//! | 0x4    | 4          | number of symbols `n`                        |
+//! | 0x8    | 12 * `n`   | `addr`, `size`, `name offset`, sorted by addr |
+//! | ...    | ...        | NUL terminated names                         |
+
+use std::io::Write;
+use std::convert::TryFrom;
+use byteorder::{ByteOrder, WriteBytesExt, LE};
+use goblin::elf::{Elf, sym};
+use crate::{Result, FirmError};
+
+pub const MAGIC: [u8; 4] = *b"SYMS";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub addr: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+impl Symbol {
+    pub fn contains_addr(&self, addr: u32) -> bool {
+        addr >= self.addr && u64::from(addr) < u64::from(self.addr) + u64::from(self.size)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Collects the function and object symbols of an ELF file with demangled names.
+    pub fn from_elf(file: &[u8]) -> Result<Self> {
+        let elf = Elf::parse(file)?;
+        let mut symbols = Vec::new();
+
+        for sym in elf.syms.iter() {
+            if sym.st_type() != sym::STT_FUNC && sym.st_type() != sym::STT_OBJECT {
+                continue;
+            }
+
+            let name = match elf.strtab.get(sym.st_name) {
+                Some(Ok(name)) if !name.is_empty() => name,
+                _ => continue,
+            };
+
+            // Thumb functions have the lowest address bit set
+            let addr = if sym.st_type() == sym::STT_FUNC { sym.st_value & !1 } else { sym.st_value };
+
+            symbols.push(Symbol {
+                addr: u32::try_from(addr).map_err(|_| FirmError::AddressOutOfRange(addr))?,
+                size: u32::try_from(sym.st_size).map_err(|_| FirmError::AddressOutOfRange(sym.st_size))?,
+                name: format!("{:#}", rustc_demangle::demangle(name)),
+            });
+        }
+
+        Ok(Self::new(symbols))
+    }
+
+    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+        symbols.sort_by_key(|symbol| symbol.addr);
+        symbols.dedup_by(|a, b| a.addr == b.addr && a.name == b.name);
+
+        Self { symbols }
+    }
+
+    /// Parses a table in the compact format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let truncated = || FirmError::InvalidSymbolTable;
+
+        if bytes.get(..4) != Some(&MAGIC[..]) {
+            return Err(FirmError::InvalidSymbolTable);
+        }
+
+        let count = LE::read_u32(bytes.get(4..8).ok_or_else(truncated)?) as usize;
+        let names_start = count.checked_mul(12)
+            .and_then(|size| size.checked_add(8))
+            .ok_or_else(truncated)?;
+        let entries = bytes.get(8..names_start).ok_or_else(truncated)?;
+        let names = &bytes[names_start..];
+        let mut symbols = Vec::with_capacity(count);
+
+        for entry in entries.chunks(12) {
+            let name_offset = LE::read_u32(&entry[8..12]) as usize;
+            let name = names.get(name_offset..).ok_or_else(truncated)?;
+            let name = name.split(|&byte| byte == 0).next().unwrap_or_default();
+
+            symbols.push(Symbol {
+                addr: LE::read_u32(&entry[0..4]),
+                size: LE::read_u32(&entry[4..8]),
+                name: String::from_utf8_lossy(name).into_owned(),
+            });
+        }
+
+        Ok(Self::new(symbols))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut names = Vec::new();
+
+        bytes.extend_from_slice(&MAGIC);
+        bytes.write_u32::<LE>(self.symbols.len() as u32).unwrap();
+
+        for symbol in &self.symbols {
+            bytes.write_u32::<LE>(symbol.addr).unwrap();
+            bytes.write_u32::<LE>(symbol.size).unwrap();
+            bytes.write_u32::<LE>(names.len() as u32).unwrap();
+            names.write_all(symbol.name.as_bytes()).unwrap();
+            names.push(0);
+        }
+
+        bytes.extend_from_slice(&names);
+        bytes
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn extend(&mut self, other: SymbolTable) {
+        let mut symbols = std::mem::take(&mut self.symbols);
+        symbols.extend(other.symbols);
+
+        *self = Self::new(symbols);
+    }
+
+    /// Finds the symbol containing `addr` and returns it together with the offset into it.
+    /// Symbols without a size (e.g. assembly labels) cover everything up to the next symbol.
+    pub fn lookup(&self, addr: u32) -> Option<(&Symbol, u32)> {
+        let preceding = &self.symbols[..self.symbols.partition_point(|symbol| symbol.addr <= addr)];
+
+        preceding.iter().rev()
+            .find(|symbol| symbol.contains_addr(addr))
+            .or_else(|| preceding.last().filter(|symbol| symbol.size == 0))
+            .map(|symbol| (symbol, addr - symbol.addr))
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::Path;
+use std::convert::TryFrom;
+use crate::Result;
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal number.
+pub fn parse_u32(s: &str) -> Result<u32, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+
+    result.map_err(|err| format!("Invalid number '{}': {}", s, err))
+}
+
+pub fn parse_u8(s: &str) -> Result<u8, String> {
+    let n = parse_u32(s)?;
+
+    u8::try_from(n).map_err(|_| format!("{} does not fit into a byte", s))
+}
+
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err).into())
+}
+
+pub fn write(path: &Path, data: &[u8]) -> Result {
+    fs::write(path, data).map_err(|err| format!("Failed to write {}: {}", path.display(), err).into())
+}
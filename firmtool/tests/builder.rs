@@ -0,0 +1,90 @@
+//! Section layout of the `Builder`: overlap detection and ordering.
+
+use firmtool::{Builder, CopyMethod, FirmError, Section};
+
+fn section(addr: u32, size: usize) -> Section {
+    Section::new(addr, CopyMethod::NDMA, vec![0; size]).unwrap()
+}
+
+/// `(offset, addr, size)` of every section in image order.
+fn layout(builder: &Builder) -> Vec<(u32, u32, u32)> {
+    builder.layout().unwrap().iter()
+        .map(|section| (section.offset, section.addr, section.size))
+        .collect()
+}
+
+#[test]
+fn overlapping_sections_are_rejected() {
+    let mut builder = Builder::new();
+    builder
+        .arm9_entrypoint(0x0800_0000)
+        .add_section(section(0x0800_0000, 0x400))
+        .add_section(section(0x2000_0000, 0x200))
+        .add_section(section(0x0800_0200, 0x200));
+
+    match builder.layout() {
+        Err(FirmError::SectionOverlap { first, first_range, second, second_range }) => {
+            assert_eq!((first, first_range), (0, 0x0800_0000..0x0800_0400));
+            assert_eq!((second, second_range), (2, 0x0800_0200..0x0800_0400));
+        }
+        other => panic!("expected an overlap, got {:?}", other.map(|_| ())),
+    }
+
+    assert!(matches!(builder.build(), Err(FirmError::SectionOverlap { .. })));
+}
+
+#[test]
+fn adjacent_sections_do_not_overlap() {
+    let mut builder = Builder::new();
+    builder
+        .arm9_entrypoint(0x0800_0000)
+        .add_section(section(0x0800_0000, 0x200))
+        .add_section(section(0x0800_0200, 0x400));
+
+    assert!(!builder.sections()[0].overlaps(&builder.sections()[1]));
+    assert_eq!(layout(&builder), [
+        (0x200, 0x0800_0000, 0x200),
+        (0x400, 0x0800_0200, 0x400),
+    ]);
+    assert!(builder.build().is_ok());
+}
+
+#[test]
+fn sort_by_address_orders_offsets() {
+    let mut builder = Builder::new();
+    builder
+        .add_section(section(0x2000_0000, 0x400))
+        .add_section(section(0x1FF8_0000, 0x200))
+        .add_section(section(0x0800_0000, 0x600));
+
+    assert_eq!(layout(&builder), [
+        (0x200, 0x2000_0000, 0x400),
+        (0x600, 0x1FF8_0000, 0x200),
+        (0x800, 0x0800_0000, 0x600),
+    ]);
+
+    builder.sort_by_address(true);
+
+    assert_eq!(layout(&builder), [
+        (0x200, 0x0800_0000, 0x600),
+        (0x800, 0x1FF8_0000, 0x200),
+        (0xA00, 0x2000_0000, 0x400),
+    ]);
+    // Insertion order is kept for everything else
+    assert_eq!(builder.sections()[0].addr(), 0x2000_0000);
+}
+
+#[test]
+fn overlaps_are_reported_in_sorted_order() {
+    let mut builder = Builder::new();
+    builder
+        .sort_by_address(true)
+        .add_section(section(0x0800_0200, 0x200))
+        .add_section(section(0x2000_0000, 0x200))
+        .add_section(section(0x0800_0000, 0x400));
+
+    match builder.layout() {
+        Err(FirmError::SectionOverlap { first, second, .. }) => assert_eq!((first, second), (0, 1)),
+        other => panic!("expected an overlap, got {:?}", other.map(|_| ())),
+    }
+}
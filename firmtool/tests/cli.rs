@@ -0,0 +1,225 @@
+//! Runs the firmtool binary against the fixtures, for behaviour that lives in the commands.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use firmtool::{CopyMethod, Firm};
+
+fn firmtool(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_firmtool"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+        .output()
+        .expect("failed to run firmtool")
+}
+
+#[test]
+fn verify_accepts_arm9_only_image() {
+    let output = firmtool(&["verify", "tests/fixtures/four_sections.firm"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("no arm11 entrypoint"), "{}", stdout);
+}
+
+#[test]
+fn verify_accepts_arm9_arm11_image() {
+    let output = firmtool(&["verify", "tests/fixtures/arm9_arm11.firm"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn raw_section_paths_may_contain_colons() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("raw_colons");
+    let blob = dir.join("dir:v2").join("blob.bin");
+    let output = dir.join("raw.firm");
+    std::fs::create_dir_all(blob.parent().unwrap()).unwrap();
+    std::fs::write(&blob, [0x5A; 0x200]).unwrap();
+
+    let first = format!("0x08000000:{}", blob.display());
+    let second = format!("0x20000000:{}:cpu", blob.display());
+    let result = firmtool(&[
+        "build", "-o", output.to_str().unwrap(), "--arm9-entry", "0x08000000",
+        "--raw", &first, "--raw", &second,
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let firm = std::fs::read(&output).unwrap();
+    let firm = Firm::parse(&firm).unwrap();
+    let sections: Vec<_> = firm.section_headers().map(|(_, section)| (section.addr, section.copy_method)).collect();
+
+    assert_eq!(sections, [(0x0800_0000, CopyMethod::NDMA), (0x2000_0000, CopyMethod::CPU)]);
+}
+
+#[test]
+fn reproducible_builds_pad_with_zeros() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reproducible");
+    let blob = dir.join("blob.bin");
+    let output = dir.join("reproducible.firm");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&blob, [0x5A; 0x100]).unwrap();
+
+    let raw = format!("0x08000000:{}", blob.display());
+    let result = firmtool(&[
+        "build", "-o", output.to_str().unwrap(), "--arm9-entry", "0x08000000",
+        "--raw", &raw, "--reproducible",
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let firm = std::fs::read(&output).unwrap();
+    assert_eq!(firm[0x200 + 0x100..0x200 + 0x200], [0; 0x100][..]);
+
+    let result = firmtool(&["check-manifest", output.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stdout));
+}
+
+#[test]
+fn hashes_are_written_for_any_fill_byte() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("hashes");
+    let blob = dir.join("blob.bin");
+    let output = dir.join("hashes.firm");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&blob, [0x5A; 0x100]).unwrap();
+
+    let raw = format!("0x08000000:{}", blob.display());
+    let result = firmtool(&[
+        "build", "-o", output.to_str().unwrap(), "--arm9-entry", "0x08000000",
+        "--raw", &raw, "--fill", "0xAA", "--hashes",
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let firm = std::fs::read(&output).unwrap();
+    assert_eq!(firm[0x200 + 0x100..0x200 + 0x200], [0xAA; 0x100][..]);
+
+    let result = firmtool(&["check-manifest", output.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stdout));
+}
+
+#[test]
+fn sdimage_rejects_sizes_beyond_fat32() {
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("oversized.img");
+    let result = firmtool(&[
+        "sdimage", "tests/fixtures/arm9_arm11.firm", "-o", output.to_str().unwrap(), "--size", "9000000",
+    ]);
+
+    assert!(!result.status.success());
+    assert!(!output.exists());
+}
+
+#[test]
+fn extra_file_paths_may_contain_colons() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sdimage_colons");
+    let config = dir.join("dir:v2").join("config.ini");
+    let output = dir.join("sd.img");
+    std::fs::create_dir_all(config.parent().unwrap()).unwrap();
+    std::fs::write(&config, "colons survive").unwrap();
+
+    let add = format!("{}:luma/boot.ini", config.display());
+    let result = firmtool(&[
+        "sdimage", "tests/fixtures/arm9_arm11.firm", "-o", output.to_str().unwrap(), "--size", "34", "--add", &add,
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let image = std::fs::read(&output).unwrap();
+    let contains = |needle: &[u8]| image.windows(needle.len()).any(|window| window == needle);
+
+    assert!(contains(b"colons survive"));
+    assert!(contains(b"LUMA       "));
+    assert!(contains(b"BOOT    INI"));
+}
+
+#[test]
+fn memory_map_warnings_number_sorted_sections() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sorted_warnings");
+    let blob = dir.join("blob.bin");
+    let output = dir.join("sorted.firm");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&blob, [0; 0x10]).unwrap();
+
+    let vectors = format!("0x1FFFFE00:{}", blob.display());
+    let arm9 = format!("0x08000000:{}", blob.display());
+    let result = firmtool(&[
+        "build", "-o", output.to_str().unwrap(), "--arm9-entry", "0x08000000", "--sort",
+        "--raw", &vectors, "--raw", &arm9,
+    ]);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert!(result.status.success(), "{}", stderr);
+    assert!(stderr.contains("warning: section 1: overlaps the ARM11 exception vectors"), "{}", stderr);
+}
+
+#[test]
+fn diff_shows_which_custom_signatures_differ() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("diff_signatures");
+    let resigned = dir.join("resigned.firm");
+    let mut firm = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/copy_methods.firm")).unwrap();
+    firm[0x100..0x200].copy_from_slice(&[0x3C; 0x100]);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&resigned, firm).unwrap();
+
+    let output = firmtool(&["diff", "tests/fixtures/copy_methods.firm", resigned.to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("signature: custom (a5a5a5a5a5a5a5a5...) -> custom (3c3c3c3c3c3c3c3c...)"), "{}", stdout);
+}
+
+/// Writes `manifest` to a scratch directory next to a raw blob and builds it.
+fn build_manifest(test: &str, manifest: &str) -> (PathBuf, Output) {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("manifest").join(test);
+    let path = dir.join("firm.toml");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("blob.bin"), [0x5A; 0x200]).unwrap();
+    std::fs::write(&path, manifest).unwrap();
+
+    let output = firmtool(&["build", "--manifest", path.to_str().unwrap()]);
+
+    (dir, output)
+}
+
+#[test]
+fn manifest_paths_are_relative_to_the_manifest() {
+    let (dir, output) = build_manifest("relative", r#"
+        output = "manifest.firm"
+        arm9_entry = 0x08000000
+
+        [[section]]
+        raw = "blob.bin"
+        addr = 0x08000000
+    "#);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let firm = std::fs::read(dir.join("manifest.firm")).unwrap();
+    let firm = Firm::parse(&firm).unwrap();
+
+    assert_eq!(firm.section_data(0).unwrap(), &[0x5A; 0x200][..]);
+    assert!(!Path::new(env!("CARGO_MANIFEST_DIR")).join("manifest.firm").exists());
+}
+
+#[test]
+fn manifests_reject_unknown_fields() {
+    let (_, output) = build_manifest("unknown_field", r#"
+        output = "manifest.firm"
+        arm9_entry = 0x08000000
+        priorty = 1
+    "#);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown field `priorty`"));
+}
+
+#[test]
+fn manifests_reject_invalid_alignment() {
+    let (_, output) = build_manifest("align", r#"
+        output = "manifest.firm"
+        arm9_entry = 0x08000000
+        align = 64
+
+        [[section]]
+        raw = "blob.bin"
+        addr = 0x08000000
+    "#);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid alignment 64"));
+}
@@ -0,0 +1,110 @@
+//! Loads handcrafted ELF files with several `PT_LOAD` segments.
+
+use byteorder::{WriteBytesExt, LE};
+use firmtool::{Builder, CopyMethod, FirmError, Section};
+use firmtool::elf::extract_program;
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+
+struct Phdr {
+    kind: u32,
+    addr: u32,
+    data: &'static [u8],
+    mem_size: u32,
+}
+
+fn load(addr: u32, data: &'static [u8], mem_size: u32) -> Phdr {
+    Phdr { kind: PT_LOAD, addr, data, mem_size }
+}
+
+/// A 32-bit ARM executable with only the null section header,
+/// the segment contents follow the program headers.
+fn elf(entry: u32, phdrs: &[Phdr]) -> Vec<u8> {
+    let mut elf = Vec::new();
+    let mut offset = 0x34 + 0x20 * phdrs.len() as u32;
+    let shoff = offset + phdrs.iter().map(|phdr| phdr.data.len() as u32).sum::<u32>();
+
+    elf.extend_from_slice(b"\x7FELF\x01\x01\x01");
+    elf.extend_from_slice(&[0; 9]);
+    elf.write_u16::<LE>(2).unwrap(); // ET_EXEC
+    elf.write_u16::<LE>(40).unwrap(); // EM_ARM
+    elf.write_u32::<LE>(1).unwrap();
+    elf.write_u32::<LE>(entry).unwrap();
+    elf.write_u32::<LE>(0x34).unwrap();
+    elf.write_u32::<LE>(shoff).unwrap();
+    elf.write_u32::<LE>(0x0500_0000).unwrap();
+    for &field in &[0x34, 0x20, phdrs.len() as u16, 0x28, 1, 0] {
+        elf.write_u16::<LE>(field).unwrap();
+    }
+
+    for phdr in phdrs {
+        for &field in &[phdr.kind, offset, phdr.addr, phdr.addr, phdr.data.len() as u32, phdr.mem_size, 7, 4] {
+            elf.write_u32::<LE>(field).unwrap();
+        }
+
+        offset += phdr.data.len() as u32;
+    }
+
+    for phdr in phdrs {
+        elf.extend_from_slice(phdr.data);
+    }
+
+    elf.extend_from_slice(&[0; 0x28]);
+
+    elf
+}
+
+#[test]
+fn contiguous_segments_are_merged_with_zero_fill() {
+    let program = extract_program(&elf(0x0800_0000, &[
+        load(0x0800_0000, &[1; 0x10], 0x20),
+        load(0x0800_0020, &[2; 0x08], 0x08),
+        Phdr { kind: PT_NOTE, addr: 0, data: &[3; 4], mem_size: 4 },
+        load(0x0800_0028, &[4; 0x04], 0x10),
+    ])).unwrap();
+
+    assert_eq!(program.entrypoint, 0x0800_0000);
+    assert_eq!(program.segments.len(), 1);
+    assert_eq!(program.segments[0].addr, 0x0800_0000);
+    assert_eq!(program.segments[0].data, [&[1; 0x10][..], &[0; 0x10], &[2; 0x08], &[4; 0x04], &[0; 0x0C]].concat());
+}
+
+#[test]
+fn non_contiguous_segments_become_separate_sections() {
+    let program = extract_program(&elf(0x1FF8_0000, &[
+        load(0x1FF8_0000, &[1; 0x10], 0x10),
+        load(0x1FF8_0100, &[2; 0x10], 0x18),
+        load(0x0800_0000, &[], 0),
+        load(0x2000_0000, &[3; 0x04], 0x04),
+    ])).unwrap();
+    let segments: Vec<_> = program.segments.iter().map(|segment| (segment.addr, segment.data.clone())).collect();
+
+    assert_eq!(program.entrypoint, 0x1FF8_0000);
+    assert_eq!(segments, [
+        (0x1FF8_0000, vec![1; 0x10]),
+        (0x1FF8_0100, [&[2; 0x10][..], &[0; 0x08]].concat()),
+        (0x2000_0000, vec![3; 0x04]),
+    ]);
+}
+
+#[test]
+fn more_than_four_sections_are_rejected() {
+    let program = extract_program(&elf(0x0800_0000, &[
+        load(0x0800_0000, &[1; 4], 4),
+        load(0x0800_1000, &[2; 4], 4),
+        load(0x0800_2000, &[3; 4], 4),
+        load(0x0800_3000, &[4; 4], 4),
+        load(0x0800_4000, &[5; 4], 4),
+    ])).unwrap();
+    let mut builder = Builder::new();
+
+    builder.arm9_entrypoint(program.entrypoint);
+
+    for segment in program.segments {
+        builder.add_section(Section::new(segment.addr, CopyMethod::NDMA, segment.data).unwrap());
+    }
+
+    assert!(matches!(builder.layout(), Err(FirmError::TooManySections(5))));
+    assert!(matches!(builder.build(), Err(FirmError::TooManySections(5))));
+}
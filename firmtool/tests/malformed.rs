@@ -0,0 +1,93 @@
+//! Every file in `tests/malformed` has to be rejected with an error instead of a panic.
+//! The same directories serve as seed corpora for the fuzz targets in `fuzz/`.
+//!
+//! Images in `firm_layout` are well-formed but describe impossible layouts,
+//! they only have to get through the commands without a panic.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use firmtool::Firm;
+use firmtool::elf::extract_program;
+use firmtool::hashes::ContentHashes;
+use firmtool::symbols::SymbolTable;
+
+fn corpus(kind: &str) -> Vec<(PathBuf, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/malformed").join(kind);
+    let mut files = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            let data = fs::read(&path).unwrap();
+            (path, data)
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+    assert!(!files.is_empty(), "{} is empty", dir.display());
+
+    files
+}
+
+#[test]
+fn malformed_elf_files_are_rejected() {
+    for (path, data) in corpus("elf") {
+        assert!(extract_program(&data).is_err(), "{} was accepted", path.display());
+
+        let _ = SymbolTable::from_elf(&data);
+    }
+}
+
+#[test]
+fn malformed_firm_images_are_rejected() {
+    for (path, data) in corpus("firm") {
+        let hashes = Firm::parse(&data).and_then(|firm| ContentHashes::new(&firm));
+
+        assert!(hashes.is_err(), "{} was accepted", path.display());
+    }
+}
+
+#[test]
+fn malformed_symbol_tables_are_rejected() {
+    for (path, data) in corpus("symbols") {
+        assert!(SymbolTable::from_bytes(&data).is_err(), "{} was accepted", path.display());
+    }
+}
+
+/// Runs firmtool and fails the test if it panicked, errors are fine.
+fn assert_no_panic(args: &[&Path]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_firmtool"))
+        .args(args)
+        .output()
+        .expect("failed to run firmtool");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!stderr.contains("panicked"), "firmtool {:?} panicked:\n{}", args, stderr);
+}
+
+#[test]
+fn commands_survive_malformed_images() {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("malformed_extract");
+    let images = corpus("firm").into_iter().chain(corpus("firm_layout"))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+
+    for image in &images {
+        for command in &["parse", "verify", "info", "check-manifest"] {
+            assert_no_panic(&[Path::new(command), image]);
+        }
+
+        assert_no_panic(&[Path::new("info"), Path::new("--map"), image]);
+        assert_no_panic(&[Path::new("extract"), image, &out_dir, Path::new("--elf")]);
+
+        for other in &images {
+            assert_no_panic(&[Path::new("diff"), image, other]);
+        }
+    }
+}
+
+#[test]
+fn symbolize_survives_malformed_symbol_tables() {
+    for (path, _) in corpus("symbols") {
+        assert_no_panic(&[Path::new("symbolize"), &path, Path::new("0x08000000")]);
+    }
+}
@@ -0,0 +1,75 @@
+//! Load addresses and entrypoints checked against the 3DS memory map.
+
+use firmtool::{CopyMethod, Cpu};
+use firmtool::memmap::{check_entrypoint, check_section, Model, Severity};
+use Severity::{Error, Warning};
+
+const NOT_IN_A_REGION: &str = "does not lie within a single writable memory region";
+
+/// Model, address, size and copy method of a section with the issues expected for it.
+type Case = (Model, u32, u32, CopyMethod, &'static [(Severity, &'static str)]);
+
+#[test]
+fn sections() {
+    use CopyMethod::{NDMA, XDMA};
+    use Model::{O3ds, N3ds};
+
+    let cases: &[Case] = &[
+        (O3ds, 0x0800_0000, 0x1000, NDMA, &[]),
+        (O3ds, 0x1800_0000, 0x1000, XDMA, &[]),
+        (O3ds, 0x2000_0000, 0x1000, XDMA, &[]),
+        // Reserved memory
+        (O3ds, 0x07FF_F000, 0x2000, NDMA, &[(Error, "overlaps the ARM9 ITCM"), (Error, NOT_IN_A_REGION)]),
+        (O3ds, 0xFFF0_0000, 0x0200, NDMA, &[(Error, "overlaps the boot9 stack in DTCM"), (Error, NOT_IN_A_REGION)]),
+        (O3ds, 0xFFFF_0000, 0x0200, NDMA, &[(Error, "overlaps the ARM9 bootrom"), (Error, NOT_IN_A_REGION)]),
+        (O3ds, 0x0000_0000, 0x0200, NDMA, &[
+            (Error, "overlaps the ARM9 ITCM"), (Error, "overlaps the ARM11 bootrom"), (Error, NOT_IN_A_REGION),
+        ]),
+        (O3ds, 0x17E0_0000, 0x0200, NDMA, &[(Error, "overlaps the ARM11 private memory"), (Error, NOT_IN_A_REGION)]),
+        // Sections may not straddle two regions
+        (O3ds, 0x1FF7_FE00, 0x0400, NDMA, &[(Error, NOT_IN_A_REGION)]),
+        (O3ds, 0x1FFF_FE00, 0x0200, NDMA, &[(Warning, "overlaps the ARM11 exception vectors at the end of AXI WRAM")]),
+        // XDMA reachability
+        (O3ds, 0x0800_0000, 0x0200, XDMA, &[(Error, "XDMA cannot reach ARM9 memory")]),
+        (N3ds, 0x0810_0000, 0x0200, XDMA, &[(Error, "XDMA cannot reach ARM9 memory extension")]),
+        (O3ds, 0x1FF0_0000, 0x0200, XDMA, &[]),
+        // New 3DS only memory
+        (O3ds, 0x0810_0000, 0x0200, NDMA, &[(Error, "ARM9 memory extension only exists on the New 3DS")]),
+        (N3ds, 0x0810_0000, 0x0200, NDMA, &[]),
+        (O3ds, 0x2800_0000, 0x0200, XDMA, &[(Error, "N3DS extra FCRAM only exists on the New 3DS")]),
+        (N3ds, 0x2800_0000, 0x0200, XDMA, &[]),
+        (O3ds, 0x1F00_0000, 0x0200, XDMA, &[(Error, "N3DS extra memory only exists on the New 3DS")]),
+        (N3ds, 0x1F00_0000, 0x0200, XDMA, &[]),
+    ];
+
+    for &(model, addr, size, copy_method, expected) in cases {
+        let issues = check_section(model, addr, size, copy_method);
+        let issues: Vec<_> = issues.iter().map(|issue| (issue.severity, issue.message.as_str())).collect();
+
+        assert_eq!(issues.len(), expected.len(), "{:?} 0x{:08X}+0x{:X} {:?}: {:?}", model, addr, size, copy_method, issues);
+
+        for (issue, expected) in issues.iter().zip(expected) {
+            assert!(issue.0 == expected.0 && issue.1.contains(expected.1),
+                "{:?} 0x{:08X}+0x{:X} {:?}: expected {:?}, got {:?}", model, addr, size, copy_method, expected, issue);
+        }
+    }
+}
+
+#[test]
+fn entrypoints() {
+    let cases = [
+        (Cpu::Arm9, 0x0800_0000, false),
+        (Cpu::Arm9, 0x0810_0000, false),
+        (Cpu::Arm11, 0x1FF8_0000, false),
+        (Cpu::Arm11, 0x2000_0000, false),
+        (Cpu::Arm11, 0x0800_0000, true),
+        (Cpu::Arm11, 0x0810_0000, true),
+    ];
+
+    for &(cpu, entrypoint, rejected) in &cases {
+        let issue = check_entrypoint(cpu, entrypoint);
+
+        assert_eq!(issue.is_some(), rejected, "{} 0x{:08X}: {:?}", cpu, entrypoint, issue);
+        assert!(issue.iter().all(|issue| issue.is_error()));
+    }
+}
@@ -0,0 +1,120 @@
+//! Patches copies of the golden fixtures with `firmtool patch`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use firmtool::{Builder, CopyMethod, Firm, Signature};
+use firmtool::elf::write_executable;
+
+/// Header bytes every patch has to keep: priority, reserved bytes and signature.
+const RESERVED: [u8; 0x30] = [0x3C; 0x30];
+
+/// Copies a fixture into a scratch directory, with recognisable reserved bytes.
+fn scratch(test: &str, fixture: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("patch").join(test);
+    let path = dir.join(fixture);
+    let mut firm = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(fixture)).unwrap();
+    firm[0x10..0x40].copy_from_slice(&RESERVED);
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&path, firm).unwrap();
+
+    path
+}
+
+fn patch(firm: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_firmtool"))
+        .arg("patch")
+        .arg(firm)
+        .args(args)
+        .output()
+        .expect("failed to run firmtool");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+fn write(path: &Path, data: &[u8]) -> String {
+    fs::write(path, data).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+/// `(addr, copy method, data)` of every used section.
+fn sections(firm: &Firm) -> Vec<(u32, CopyMethod, Vec<u8>)> {
+    firm.section_headers()
+        .map(|(i, section)| (section.addr, section.copy_method, firm.section_data(i).unwrap().to_vec()))
+        .collect()
+}
+
+#[test]
+fn rebuilding_an_image_is_lossless() {
+    let path = scratch("lossless", "copy_methods.firm");
+    let data = fs::read(&path).unwrap();
+    let firm = Firm::parse(&data).unwrap();
+
+    assert_eq!(Builder::from_firm(&firm).unwrap().build().unwrap(), data);
+}
+
+#[test]
+fn patch_section_by_index() {
+    let path = scratch("by_index", "copy_methods.firm");
+    let original = fs::read(&path).unwrap();
+    let original = Firm::parse(&original).unwrap();
+    let raw = write(&path.with_file_name("splash.bin"), &[0x77; 0x300]);
+
+    patch(&path, &["--section", "1", "--raw", &raw]);
+
+    let patched = fs::read(&path).unwrap();
+    let patched = Firm::parse(&patched).unwrap();
+    let header = patched.header();
+    let mut expected = sections(&original);
+    expected[1] = (0x1800_0000, CopyMethod::XDMA, [&[0x77; 0x300][..], &[0xFF; 0x100]].concat());
+
+    assert_eq!(sections(&patched), expected);
+    assert_eq!(header.boot_priority, 1);
+    assert_eq!((header.arm9_entrypoint, header.arm11_entrypoint), (0x0800_0040, 0x2000_0000));
+    assert_eq!(header.reserved, RESERVED);
+    assert_eq!(header.signature(), Signature::Custom(Box::new([0xA5; 0x100])));
+}
+
+#[test]
+fn patch_section_by_address() {
+    let path = scratch("by_address", "arm9_arm11.firm");
+    let output = path.with_file_name("patched.firm");
+    let raw = write(&path.with_file_name("arm11.bin"), &[0x11; 0x200]);
+
+    patch(&path, &["--at", "0x1FF80100", "--raw", &raw, "--copy-method", "cpu", "-o", output.to_str().unwrap()]);
+
+    let original = fs::read(&path).unwrap();
+    let original = Firm::parse(&original).unwrap();
+    let patched = fs::read(&output).unwrap();
+    let patched = Firm::parse(&patched).unwrap();
+    let mut expected = sections(&original);
+    expected[1] = (0x1FF8_0000, CopyMethod::CPU, vec![0x11; 0x200]);
+
+    assert_eq!(sections(&patched), expected);
+    assert_eq!(patched.header().reserved, RESERVED);
+    assert_eq!(patched.header().signature(), Signature::NandRetail);
+}
+
+#[test]
+fn entrypoints_follow_a_patched_elf() {
+    let path = scratch("elf", "copy_methods.firm");
+    let elf = path.with_file_name("arm9.elf");
+    let mut executable = Vec::new();
+    write_executable(&mut executable, 0x0800_0000, 0x0800_0020, &[0x99; 0x280]).unwrap();
+    fs::write(&elf, executable).unwrap();
+
+    patch(&path, &["--section", "0", "--elf", elf.to_str().unwrap()]);
+
+    let patched = fs::read(&path).unwrap();
+    let patched = Firm::parse(&patched).unwrap();
+    let header = patched.header();
+
+    assert_eq!(sections(&patched)[0].0, 0x0800_0000);
+    assert_eq!(&sections(&patched)[0].2[..0x280], &[0x99; 0x280][..]);
+    // Only the entrypoint into the replaced section moves
+    assert_eq!((header.arm9_entrypoint, header.arm11_entrypoint), (0x0800_0020, 0x2000_0000));
+    assert_eq!(header.boot_priority, 1);
+    assert_eq!(header.reserved, RESERVED);
+    assert_eq!(header.signature(), Signature::Custom(Box::new([0xA5; 0x100])));
+}
@@ -0,0 +1,348 @@
+//! Checks firmtool against golden FIRM images and the independent
+//! description of the format in `formats/firm.ksy`.
+//!
+//! Run with `UPDATE_FIXTURES=1` to regenerate `tests/fixtures` after an intended format change.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use firmtool::{Builder, CopyMethod, Firm, Section, Signature};
+use firmtool::header::{self, Header};
+use serde_yaml::Value as Yaml;
+
+struct Fixture {
+    name: &'static str,
+    priority: u32,
+    arm9_entrypoint: u32,
+    arm11_entrypoint: Option<u32>,
+    signature: Signature,
+    sections: &'static [(u32, CopyMethod, usize)],
+}
+
+fn fixtures() -> Vec<Fixture> {
+    vec![
+        Fixture {
+            name: "arm9_arm11.firm",
+            priority: 0,
+            arm9_entrypoint: 0x0800_0000,
+            arm11_entrypoint: Some(0x1FF8_0000),
+            signature: Signature::NandRetail,
+            sections: &[
+                (0x0800_0000, CopyMethod::NDMA, 0x400),
+                (0x1FF8_0000, CopyMethod::XDMA, 0x600),
+            ],
+        },
+        Fixture {
+            name: "copy_methods.firm",
+            priority: 1,
+            arm9_entrypoint: 0x0800_0040,
+            arm11_entrypoint: Some(0x2000_0000),
+            signature: Signature::Custom(Box::new([0xA5; 0x100])),
+            sections: &[
+                (0x0800_0000, CopyMethod::NDMA, 0x200),
+                (0x1800_0000, CopyMethod::XDMA, 0x200),
+                (0x2000_0000, CopyMethod::CPU, 0x800),
+            ],
+        },
+        Fixture {
+            name: "four_sections.firm",
+            priority: 0xFFFF_FFFF,
+            arm9_entrypoint: 0x0808_0000,
+            arm11_entrypoint: None,
+            signature: Signature::NandRetail,
+            sections: &[
+                (0x0808_0000, CopyMethod::NDMA, 0x200),
+                (0x0800_0000, CopyMethod::NDMA, 0x1000),
+                (0x1FF8_0000, CopyMethod::XDMA, 0x200),
+                (0x2000_0000, CopyMethod::CPU, 0x200),
+            ],
+        },
+    ]
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn build(fixture: &Fixture) -> Vec<u8> {
+    let mut builder = Builder::new();
+
+    builder
+        .boot_priority(fixture.priority)
+        .arm9_entrypoint(fixture.arm9_entrypoint)
+        .arm11_entrypoint(fixture.arm11_entrypoint)
+        .signature(fixture.signature.clone());
+
+    for (i, &(addr, copy_method, size)) in fixture.sections.iter().enumerate() {
+        let data = (0..size).map(|j| (i * 31 + j) as u8).collect();
+        builder.add_section(Section::new(addr, copy_method, data).unwrap());
+    }
+
+    builder.build().unwrap()
+}
+
+/// Rebuilds an image from nothing but what could be parsed out of it.
+fn rebuild(firm: &Firm) -> Vec<u8> {
+    let header = firm.header();
+    let mut builder = Builder::new();
+
+    builder
+        .boot_priority(header.boot_priority)
+        .arm9_entrypoint(header.arm9_entrypoint)
+        .arm11_entrypoint(Some(header.arm11_entrypoint).filter(|&entry| entry != 0))
+        .signature(header.signature());
+
+    for (i, section) in firm.section_headers() {
+        let data = firm.section_data(i).unwrap().to_vec();
+        builder.add_section(Section::new(section.addr, section.copy_method, data).unwrap());
+    }
+
+    builder.build().unwrap()
+}
+
+#[test]
+fn builds_match_golden_fixtures() {
+    let update = env::var_os("UPDATE_FIXTURES").is_some();
+
+    for fixture in fixtures() {
+        let firm = build(&fixture);
+        let path = fixture_path(fixture.name);
+
+        if update {
+            fs::write(&path, &firm).unwrap();
+        }
+
+        let golden = fs::read(&path).unwrap();
+        assert!(firm == golden, "{} differs from the golden image", fixture.name);
+    }
+}
+
+#[test]
+fn build_parse_build_is_identical() {
+    for fixture in fixtures() {
+        let golden = fs::read(fixture_path(fixture.name)).unwrap();
+        let firm = Firm::parse(&golden).unwrap();
+
+        assert!(rebuild(&firm) == golden, "{} changed after a round trip", fixture.name);
+
+        let mut header = Vec::new();
+        firm.header().to_writer(&mut header).unwrap();
+        assert_eq!(header, &golden[..header::SIZE as usize], "{}", fixture.name);
+    }
+}
+
+#[test]
+fn header_size_is_enforced() {
+    let mut header = Vec::new();
+    Header::new().to_writer(&mut header).unwrap();
+    assert_eq!(header.len(), header::SIZE as usize);
+
+    for fixture in fixtures() {
+        let golden = fs::read(fixture_path(fixture.name)).unwrap();
+        let firm = Firm::parse(&golden).unwrap();
+
+        // Sections are laid out back to back right after the header
+        let mut offset = header::SIZE;
+
+        for (i, section) in firm.section_headers() {
+            assert_eq!(section.offset, offset, "{} section {}", fixture.name, i);
+            offset += section.size;
+        }
+
+        assert_eq!(offset as usize, golden.len(), "{}", fixture.name);
+        assert!(Firm::parse(&golden[..header::SIZE as usize - 1]).is_err(), "{}", fixture.name);
+    }
+
+    // Sections overlapping the header are not handed out
+    let mut golden = fs::read(fixture_path("arm9_arm11.firm")).unwrap();
+    let first_offset = 0x40;
+    golden[first_offset..first_offset + 4].copy_from_slice(&(header::SIZE - 0x10).to_le_bytes());
+    assert!(Firm::parse(&golden).unwrap().section_data(0).is_none());
+}
+
+#[test]
+fn ksy_agrees_with_header() {
+    let ksy = Ksy::load();
+
+    for fixture in fixtures() {
+        let golden = fs::read(fixture_path(fixture.name)).unwrap();
+        let firm = Firm::parse(&golden).unwrap();
+        let header = firm.header();
+        let mut pos = 0;
+        let root = ksy.parse_type(&ksy.root, &golden, &mut pos);
+        let ksy_header = root.field("header");
+
+        assert_eq!(ksy.header_size(&golden), header::SIZE as usize, "{}", fixture.name);
+        assert_eq!(ksy_header.field("priority").int(), u64::from(header.boot_priority), "{}", fixture.name);
+        assert_eq!(ksy_header.field("arm11_entry").int(), u64::from(header.arm11_entrypoint), "{}", fixture.name);
+        assert_eq!(ksy_header.field("arm9_entry").int(), u64::from(header.arm9_entrypoint), "{}", fixture.name);
+        assert_eq!(ksy_header.field("reserved").bytes(), &header.reserved[..], "{}", fixture.name);
+        assert_eq!(ksy_header.field("signature").bytes(), &header.rsa_signature[..], "{}", fixture.name);
+
+        let ksy_sections = ksy_header.field("section_headers").array();
+        assert_eq!(ksy_sections.len(), header.section_headers.len());
+
+        for (i, (ksy_section, section)) in ksy_sections.iter().zip(&header.section_headers).enumerate() {
+            let context = format!("{} section {}", fixture.name, i);
+
+            assert_eq!(ksy_section.field("offset").int(), u64::from(section.offset), "{}", context);
+            assert_eq!(ksy_section.field("address").int(), u64::from(section.addr), "{}", context);
+            assert_eq!(ksy_section.field("size").int(), u64::from(section.size), "{}", context);
+            assert_eq!(ksy_section.field("sha256").bytes(), &section.sha256[..], "{}", context);
+            assert_eq!(
+                ksy_section.field("copy_method").enum_name(),
+                format!("{:?}", section.copy_method).to_lowercase(),
+                "{}", context,
+            );
+
+            if !section.is_empty() {
+                assert_eq!(ksy_section.field("section").bytes(), firm.section_data(i).unwrap(), "{}", context);
+            }
+        }
+    }
+}
+
+/// Interprets the subset of Kaitai Struct used by `formats/firm.ksy`:
+/// little endian `u4`, fixed `size` and `contents`, user types,
+/// `repeat: expr` and instances positioned by fields of the same type.
+struct Ksy {
+    root: Yaml,
+    types: Yaml,
+    enums: Yaml,
+}
+
+#[derive(Debug)]
+enum Value {
+    Int(u64),
+    Enum(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+}
+
+impl Ksy {
+    fn load() -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../formats/firm.ksy");
+        let ksy: Yaml = serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+
+        assert_eq!(ksy["meta"]["endian"].as_str(), Some("le"), "only little endian specs are supported");
+
+        Self {
+            root: ksy.clone(),
+            types: ksy["types"].clone(),
+            enums: ksy["enums"].clone(),
+        }
+    }
+
+    /// Size of the `header` type as laid out by the spec.
+    fn header_size(&self, data: &[u8]) -> usize {
+        let mut pos = 0;
+        self.parse_type(&self.types["header"], data, &mut pos);
+        pos
+    }
+
+    fn parse_type(&self, def: &Yaml, data: &[u8], pos: &mut usize) -> Value {
+        let mut fields = Vec::new();
+
+        for attr in def["seq"].as_sequence().into_iter().flatten() {
+            let id = attr["id"].as_str().unwrap().to_string();
+            let value = match attr["repeat"].as_str() {
+                Some("expr") => {
+                    let count = attr["repeat-expr"].as_u64().unwrap();
+                    Value::Array((0..count).map(|_| self.parse_attr(attr, data, pos)).collect())
+                }
+                Some(repeat) => panic!("unsupported repeat: {}", repeat),
+                None => self.parse_attr(attr, data, pos),
+            };
+
+            fields.push((id, value));
+        }
+
+        for (id, instance) in def["instances"].as_mapping().into_iter().flatten() {
+            assert_eq!(instance["io"].as_str(), Some("_root._io"), "unsupported instance io");
+
+            let field = |name: &Yaml| fields.iter()
+                .find(|(id, _)| Some(id.as_str()) == name.as_str())
+                .map(|(_, value)| value.int() as usize)
+                .unwrap_or_else(|| panic!("unsupported instance expression {:?}", name));
+            let start = field(&instance["pos"]);
+            let end = start + field(&instance["size"]);
+            let value = Value::Bytes(data.get(start..end).unwrap_or_default().to_vec());
+
+            fields.push((id.as_str().unwrap().to_string(), value));
+        }
+
+        Value::Struct(fields)
+    }
+
+    fn parse_attr(&self, attr: &Yaml, data: &[u8], pos: &mut usize) -> Value {
+        let mut take = |size: usize| {
+            let bytes = data[*pos..*pos + size].to_vec();
+            *pos += size;
+            bytes
+        };
+
+        if let Some(contents) = attr["contents"].as_str() {
+            let bytes = take(contents.len());
+            assert_eq!(bytes, contents.as_bytes());
+            return Value::Bytes(bytes);
+        }
+
+        match attr["type"].as_str() {
+            Some("u4") => {
+                let bytes = take(4);
+                let n = u64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+
+                match attr["enum"].as_str() {
+                    Some(name) => {
+                        let variant = &self.enums[name][n as usize];
+                        Value::Enum(variant.as_str().unwrap_or_else(|| panic!("{} is not a {}", n, name)).to_string())
+                    }
+                    None => Value::Int(n),
+                }
+            }
+            Some(name) => self.parse_type(&self.types[name], data, pos),
+            None => Value::Bytes(take(attr["size"].as_u64().unwrap() as usize)),
+        }
+    }
+}
+
+impl Value {
+    fn field(&self, name: &str) -> &Value {
+        match self {
+            Value::Struct(fields) => fields.iter()
+                .find(|(id, _)| id == name)
+                .map(|(_, value)| value)
+                .unwrap_or_else(|| panic!("no field {}", name)),
+            _ => panic!("{:?} is not a struct", self),
+        }
+    }
+
+    fn int(&self) -> u64 {
+        match self {
+            Value::Int(n) => *n,
+            _ => panic!("{:?} is not an integer", self),
+        }
+    }
+
+    fn enum_name(&self) -> &str {
+        match self {
+            Value::Enum(name) => name,
+            _ => panic!("{:?} is not an enum", self),
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Value::Bytes(bytes) => bytes,
+            _ => panic!("{:?} is not a byte array", self),
+        }
+    }
+
+    fn array(&self) -> &[Value] {
+        match self {
+            Value::Array(values) => values,
+            _ => panic!("{:?} is not an array", self),
+        }
+    }
+}